@@ -0,0 +1,143 @@
+// The code generated by `#[program]` in Anchor 0.31 still calls the deprecated
+// `AccountInfo::realloc`, which fails clippy with `-D warnings` otherwise
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+declare_id!("BGC3tHQx1ZEyhtJcyG1UaVqcmkShGECcqGBcJmq2YQeS");
+
+/// A minimal staking pool implementing the `deposit` interface `claim_and_stake` calls.
+/// Only used by the tests, a pool with a `shortfall` pulls that many tokens less than asked for.
+#[program]
+pub mod mock_staking {
+    use super::*;
+
+    pub fn initialize_pool(ctx: Context<InitializePool>, shortfall: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.mint = ctx.accounts.mint.key();
+        pool.vault = ctx.accounts.pool_vault.key();
+        pool.shortfall = shortfall;
+        pool.total_staked = 0;
+        Ok(())
+    }
+
+    pub fn open_position(ctx: Context<OpenPosition>) -> Result<()> {
+        let position = &mut ctx.accounts.position;
+        position.pool = ctx.accounts.pool.key();
+        position.owner = ctx.accounts.owner.key();
+        position.staked = 0;
+        Ok(())
+    }
+
+    /// Pulls `amount` minus the pool's shortfall out of `source` and credits it to the position
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        let staked = amount.saturating_sub(ctx.accounts.pool.shortfall);
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.source.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.pool_vault.to_account_info(),
+                authority: ctx.accounts.source_authority.to_account_info(),
+            },
+        );
+        token_interface::transfer_checked(cpi_ctx, staked, ctx.accounts.mint.decimals)?;
+
+        ctx.accounts.position.staked += staked;
+        ctx.accounts.pool.total_staked += staked;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Pool {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub shortfall: u64,
+    pub total_staked: u64,
+}
+
+impl Pool {
+    pub const MAX_SIZE: usize = 32 + 32 + 8 + 8;
+}
+
+#[account]
+pub struct Position {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub staked: u64,
+}
+
+impl Position {
+    pub const MAX_SIZE: usize = 32 + 32 + 8;
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    #[account(init, space = 8 + Pool::MAX_SIZE, payer = payer)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        seeds = [b"pool_vault", pool.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        payer = payer,
+    )]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    owner: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        space = 8 + Position::MAX_SIZE,
+        seeds = [b"position", pool.key().as_ref(), owner.key().as_ref()],
+        bump,
+        payer = owner,
+    )]
+    pub position: Account<'info, Position>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts in the order of `token_vesting::staking::Deposit`
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut, has_one = pool, has_one = owner)]
+    pub position: Account<'info, Position>,
+
+    pub owner: Signer<'info>,
+
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+
+    pub source_authority: Signer<'info>,
+
+    #[account(mut, address = pool.vault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
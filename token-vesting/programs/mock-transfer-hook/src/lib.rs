@@ -0,0 +1,109 @@
+// The code generated by `#[program]` in Anchor 0.31 still calls the deprecated
+// `AccountInfo::realloc`, which fails clippy with `-D warnings` otherwise
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use spl_discriminator::SplDiscriminate;
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+declare_id!("G7ZXVk51v7TtD2TiFGDTX5v8Gzi5V68D2p4drAuKPXdA");
+
+/// A transfer hook that only counts the transfers of a mint.
+/// Only used by the tests, the counter is the one extra account the hook asks the token program for.
+#[program]
+pub mod mock_transfer_hook {
+    use super::*;
+
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        // The counter is derived from the mint, which is account 1 of `Execute`
+        let extra_account_metas = [ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"counter".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+            ],
+            false,
+            true,
+        )?];
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+            &extra_account_metas,
+        )?;
+
+        ctx.accounts.counter.transfers = 0;
+        Ok(())
+    }
+
+    /// Called by the token program on every `transfer_checked` of the mint
+    #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn execute(ctx: Context<Execute>, _amount: u64) -> Result<()> {
+        ctx.accounts.counter.transfers += 1;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct Counter {
+    pub transfers: u64,
+}
+
+impl Counter {
+    pub const MAX_SIZE: usize = 8;
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    /// CHECK: Written as a TLV extra account meta list, not an Anchor account
+    #[account(
+        init,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump,
+        space = ExtraAccountMetaList::size_of(1)?,
+        payer = payer,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        space = 8 + Counter::MAX_SIZE,
+        seeds = [b"counter", mint.key().as_ref()],
+        bump,
+        payer = payer,
+    )]
+    pub counter: Account<'info, Counter>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts in the order the token program passes them to the hook
+#[derive(Accounts)]
+pub struct Execute<'info> {
+    #[account(token::mint = mint)]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(token::mint = mint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: The source owner or delegate, already checked by the token program
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: The extra account meta list of the mint
+    #[account(seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"counter", mint.key().as_ref()], bump)]
+    pub counter: Account<'info, Counter>,
+}
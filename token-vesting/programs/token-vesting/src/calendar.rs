@@ -0,0 +1,183 @@
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Days since 1970-01-01 of the proleptic Gregorian date `year-month-day` (UTC)
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // Shift the year to start in March so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The proleptic Gregorian date `(year, month, day)` of `days` since 1970-01-01 (UTC)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Adds `months` calendar months to the unix `timestamp`, keeping the time of day.
+/// Days past the end of the target month are clamped to its last day, so Jan 31 + 1 month is Feb 28 (or 29).
+pub fn add_months(timestamp: i64, months: i64) -> Option<i64> {
+    let days = timestamp.div_euclid(SECONDS_PER_DAY);
+    let time_of_day = timestamp.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    let month_index = (year.checked_mul(12)?)
+        .checked_add(month as i64 - 1)?
+        .checked_add(months)?;
+    let target_year = month_index.div_euclid(12);
+    let target_month = month_index.rem_euclid(12) as u32 + 1;
+    let target_day = day.min(days_in_month(target_year, target_month));
+
+    days_from_civil(target_year, target_month, target_day)
+        .checked_mul(SECONDS_PER_DAY)?
+        .checked_add(time_of_day)
+}
+
+/// Whole calendar months from `from` to `to`, the largest `n` with `add_months(from, n) <= to`
+pub fn months_between(from: i64, to: i64) -> Option<i64> {
+    if to < from {
+        return Some(0);
+    }
+    let (from_year, from_month, _) = civil_from_days(from.div_euclid(SECONDS_PER_DAY));
+    let (to_year, to_month, _) = civil_from_days(to.div_euclid(SECONDS_PER_DAY));
+    let months = (to_year - from_year)
+        .checked_mul(12)?
+        .checked_add(to_month as i64 - from_month as i64)?;
+    if add_months(from, months)? > to {
+        Some(months - 1)
+    } else {
+        Some(months)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(year: i64, month: u32, day: u32) -> i64 {
+        days_from_civil(year, month, day) * SECONDS_PER_DAY
+    }
+
+    #[test]
+    fn civil_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for days in [-800_000, -1, 0, 59, 11_016, 11_017, 19_782, 47_540, 800_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(2024));
+        assert!(is_leap_year(2000));
+        assert!(!is_leap_year(2100));
+        assert!(!is_leap_year(2023));
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+    }
+
+    #[test]
+    fn month_end_is_clamped() {
+        assert_eq!(
+            add_months(timestamp(2023, 1, 31), 1),
+            Some(timestamp(2023, 2, 28))
+        );
+        assert_eq!(
+            add_months(timestamp(2024, 1, 31), 1),
+            Some(timestamp(2024, 2, 29))
+        );
+        assert_eq!(
+            add_months(timestamp(2024, 3, 31), 1),
+            Some(timestamp(2024, 4, 30))
+        );
+        // Clamping does not carry over, the day comes back once the month is long enough
+        assert_eq!(
+            add_months(timestamp(2024, 1, 31), 2),
+            Some(timestamp(2024, 3, 31))
+        );
+        assert_eq!(
+            add_months(timestamp(2024, 12, 31), -10),
+            Some(timestamp(2024, 2, 29))
+        );
+    }
+
+    #[test]
+    fn years_from_a_leap_day() {
+        assert_eq!(
+            add_months(timestamp(2024, 2, 29), 12),
+            Some(timestamp(2025, 2, 28))
+        );
+        assert_eq!(
+            add_months(timestamp(2024, 2, 29), 48),
+            Some(timestamp(2028, 2, 29))
+        );
+        assert_eq!(
+            add_months(timestamp(2096, 2, 29), 48),
+            Some(timestamp(2100, 2, 28))
+        );
+    }
+
+    #[test]
+    fn add_months_keeps_time_of_day() {
+        let start = timestamp(2023, 12, 15) + 13 * 3600 + 42;
+        assert_eq!(
+            add_months(start, 3),
+            Some(timestamp(2024, 3, 15) + 13 * 3600 + 42)
+        );
+    }
+
+    #[test]
+    fn whole_months_between() {
+        let start = timestamp(2024, 1, 31);
+        assert_eq!(months_between(start, start), Some(0));
+        assert_eq!(months_between(start, timestamp(2024, 2, 28)), Some(0));
+        assert_eq!(months_between(start, timestamp(2024, 2, 29)), Some(1));
+        assert_eq!(months_between(start, timestamp(2024, 3, 30)), Some(1));
+        assert_eq!(months_between(start, timestamp(2024, 3, 31)), Some(2));
+        assert_eq!(months_between(start, timestamp(2025, 1, 31)), Some(12));
+        assert_eq!(months_between(start, timestamp(2023, 12, 31)), Some(0));
+
+        let mid_day = timestamp(2024, 5, 10) + 12 * 3600;
+        assert_eq!(months_between(mid_day, timestamp(2024, 6, 10)), Some(0));
+        assert_eq!(
+            months_between(mid_day, timestamp(2024, 6, 10) + 12 * 3600),
+            Some(1)
+        );
+    }
+}
@@ -65,4 +65,96 @@ pub enum TokenVestingError {
     /// Beneficiary has more token then total assigned token after claiming
     #[msg("Beneficiary claimed more than total tokens")]
     OverClaimed,
+
+    /// The cliff must be non-negative and can not exceed the vesting duration
+    #[msg("Cliff duration is invalid")]
+    InvalidCliff,
+
+    /// Claim attempted before the cliff has passed
+    #[msg("Vesting cliff has not been reached yet")]
+    CliffNotReached,
+
+    /// The start time is further in the past or future than allowed
+    #[msg("Start time is outside the allowed window")]
+    InvalidStartTime,
+
+    /// Only the grantor of the vesting can perform this action
+    #[msg("Unauthorized grantor")]
+    UnauthorizedGrantor,
+
+    /// The grant was created without the revocable flag
+    #[msg("Vesting is not revocable")]
+    NotRevocable,
+
+    /// The grant has been revoked by the grantor
+    #[msg("Vesting has been revoked")]
+    VestingRevoked,
+
+    /// Tranches are empty, too many, or their timestamps are not increasing from the start
+    #[msg("Invalid tranche schedule")]
+    InvalidTrancheSchedule,
+
+    /// The tranche amounts do not add up to the total amount
+    #[msg("Tranche amounts do not sum to total amount")]
+    TrancheAmountMismatch,
+
+    /// The vault still holds tokens that belong to an unsettled grant
+    #[msg("Vesting is not settled yet")]
+    VestingNotSettled,
+
+    /// The Merkle proof does not match the pool's root
+    #[msg("Invalid Merkle proof")]
+    InvalidMerkleProof,
+
+    /// The operation is not available for the schedule of this grant
+    #[msg("Operation not supported for this vesting schedule")]
+    UnsupportedSchedule,
+
+    /// The crank fee exceeds the allowed maximum
+    #[msg("Crank fee is too high")]
+    CrankFeeTooHigh,
+
+    /// The new beneficiary is the current one or the default pubkey
+    #[msg("Invalid beneficiary")]
+    InvalidBeneficiary,
+
+    /// The vesting account uses an older layout and has to be migrated
+    #[msg("Vesting account layout is outdated, migrate it first")]
+    OutdatedLayout,
+
+    /// The vesting account already uses the current layout
+    #[msg("Vesting account is already migrated")]
+    AlreadyMigrated,
+
+    /// Graded weights do not match the periods or do not sum to 10_000 basis points
+    #[msg("Graded weights must cover every period and sum to 10000 basis points")]
+    InvalidWeights,
+
+    /// The acceleration is empty, above 100% or there is nothing left to accelerate
+    #[msg("Invalid acceleration")]
+    InvalidAcceleration,
+
+    /// The split share is empty, above 100% or there is nothing unvested to split
+    #[msg("Invalid split")]
+    InvalidSplit,
+
+    /// The instruction is not available for grants claimed through a position NFT
+    #[msg("Not supported for tokenized vesting positions")]
+    TokenizedPosition,
+
+    /// The lockup ends before the vesting starts
+    #[msg("Unlock time must not be before the start time")]
+    InvalidUnlockTime,
+
+    /// Vested tokens are locked up until the unlock time
+    #[msg("Tokens are locked until the unlock time")]
+    TokensLocked,
+
+    /// The staking pool left part of the claimed tokens in the stake escrow
+    #[msg("Staking pool did not take the whole deposit")]
+    IncompleteStakeDeposit,
+
+    /// The vault holds nothing beyond what is still owed to the beneficiary
+    #[msg("Vault holds no surplus")]
+    NoSurplus,
 }
@@ -0,0 +1,159 @@
+use anchor_lang::prelude::*;
+
+use crate::state::PeriodUnit;
+
+/// Emitted when a grant is initialized and its vault funded.
+/// Pool grants are announced on their first claim, with the claim status account as `vesting_account`.
+/// `template` is the template the grant was created from, default pubkey when it was not.
+/// `cliff_duration` and `duration` are in seconds, as stored on the grant, also for calendar periods.
+#[event]
+pub struct GrantCreated {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub grantor: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub total_amount: u64,
+    pub start_time: i64,
+    pub cliff_duration: i64,
+    pub vesting_period: i64,
+    pub period_unit: PeriodUnit,
+    pub duration: i64,
+    pub revocable: bool,
+    pub template: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted on every claim, `amount` is what left the vault before any Token-2022 transfer fee
+/// and `received_amount` what reached the beneficiary, after that fee and any crank fee.
+/// Pool claims report the claim status account as `vesting_account` and the pool index as `index`.
+#[event]
+pub struct TokensClaimed {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub received_amount: u64,
+    pub claimed_amount: u64,
+    pub total_amount: u64,
+    pub passed_periods: i64,
+    pub timestamp: i64,
+}
+
+/// Emitted when the grantor tops up a grant
+#[event]
+pub struct GrantToppedUp {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub grantor: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted when the beneficiary role of a grant is reassigned
+#[event]
+pub struct BeneficiaryTransferred {
+    pub vesting_account: Pubkey,
+    pub previous_beneficiary: Pubkey,
+    pub new_beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted when the beneficiary names or removes a claim delegate, removal reports the default pubkey
+#[event]
+pub struct ClaimDelegateSet {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub claim_delegate: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted when the grantor revokes a grant
+#[event]
+pub struct GrantRevoked {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub settled_amount: u64,
+    pub returned_amount: u64,
+    pub vested_amount: u64,
+    pub passed_periods: i64,
+    pub timestamp: i64,
+}
+
+/// Emitted when the grantor accelerates a grant
+#[event]
+pub struct GrantAccelerated {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub accelerated_amount: u64,
+    pub vested_amount: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted when part of a grant is split off into a new grant,
+/// the new grant is announced with its own `GrantCreated`.
+/// `amount` moved to the new vault. `pre_claimed_amount` is the new grant's share of what had
+/// vested before the split, it stays with the current beneficiary and starts out as claimed on the
+/// new grant without a `TokensClaimed`.
+#[event]
+pub struct GrantSplit {
+    pub vesting_account: Pubkey,
+    pub new_vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub new_beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub new_index: u64,
+    pub amount: u64,
+    pub pre_claimed_amount: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by the claim that transfers the last tokens of a grant,
+/// or by a revoke that settles the vested tokens straight away
+#[event]
+pub struct GrantCompleted {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub total_amount: u64,
+    pub passed_periods: i64,
+    pub timestamp: i64,
+}
+
+/// Emitted when a settled grant and its vault are closed
+#[event]
+pub struct GrantClosed {
+    pub vesting_account: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub claimed_amount: u64,
+    pub returned_amount: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted when the grantor withdraws tokens held by the vault on top of the unclaimed grant
+#[event]
+pub struct SurplusWithdrawn {
+    pub vesting_account: Pubkey,
+    pub grantor: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
@@ -1,17 +1,47 @@
+// The code generated by `#[program]` in Anchor 0.31 still calls the deprecated
+// `AccountInfo::realloc`, which fails clippy with `-D warnings` otherwise
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{self, Mint, Token, TokenAccount, Transfer as SplTransfer},
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::spl_token_2022::instruction::AuthorityType;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, MintTo, SetAuthority, TokenAccount, TokenInterface,
 };
 
+mod calendar;
 mod error;
+mod events;
+mod math;
+mod merkle;
+mod staking;
 mod state;
+mod token_utils;
 
 use error::TokenVestingError;
-use state::TokenVesting;
+use events::{
+    BeneficiaryTransferred, ClaimDelegateSet, GrantAccelerated, GrantClosed, GrantCompleted,
+    GrantCreated, GrantRevoked, GrantSplit, GrantToppedUp, SurplusWithdrawn, TokensClaimed,
+};
+use state::{
+    Acceleration, ClaimStatus, ClaimableAmount, LegacyTokenVesting, PeriodUnit, TokenVesting,
+    VestingCurve, VestingPool, VestingSchedule, VestingTemplate,
+};
 
 declare_id!("5qRj7P1BnXSTnhWBi6YBEBSZoYax8wZd9K92kPsj7Xeq");
 
+/// How far in the past a vesting schedule may start, used to backdate grants to a hire date (4 years)
+const MAX_START_TIME_BACKDATE: i64 = 4 * 365 * 24 * 60 * 60;
+/// How far in the future a vesting schedule may start, used to pre-fund grants (1 year)
+const MAX_START_TIME_DELAY: i64 = 365 * 24 * 60 * 60;
+/// Upper bound of the fee a grantor can grant to crankers, in basis points (1%)
+const MAX_CRANK_FEE_BPS: u16 = 100;
+/// Basis points in 100%
+const BPS_DENOMINATOR: u64 = 10_000;
+/// Stored as the mint of grants that vest native SOL instead of an SPL token
+const NATIVE_SOL_MINT: Pubkey = Pubkey::new_from_array([0; 32]);
+
 #[program]
 pub mod token_vesting {
 
@@ -19,49 +49,64 @@ pub mod token_vesting {
 
     /// Initializes a vesting schedule for a beneficiary.
     /// Transfers `total_amount` of TOKENS to a vault PDA.
-    pub fn initialize_vesting(
-        ctx: Context<InitializeVesting>,
+    /// Nothing can be claimed until `cliff_duration` seconds have passed since the start.
+    /// `start_time` may be backdated or scheduled in the future within the allowed window.
+    /// The signer becomes the grantor, who can revoke the grant when it is `revocable`.
+    /// With a `Linear` curve tokens vest every second instead of in whole periods.
+    /// A `Tranched` curve releases its own list of tranches, `vesting_period` and `duration` are ignored.
+    /// A `Graded` curve weights every period in basis points, the weights must sum to 10_000.
+    /// With a non-zero `unlock_time` vested tokens stay locked in the vault until then.
+    /// With a calendar `period_unit` the `vesting_period`, `duration` and `cliff_duration` are counted
+    /// in months, quarters or years from `start_time` instead of seconds.
+    /// When the position accounts are provided the grant is tokenized, a 1-of-1 position NFT is
+    /// minted to the beneficiary and whoever holds it can claim. Tokenized grants are not revocable.
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeVesting<'info>>,
         mint: Pubkey,
         beneficiary: Pubkey,
         vesting_period: i64,
         duration: i64,
         total_amount: u64,
         index: u64,
+        cliff_duration: i64,
+        start_time: i64,
+        revocable: bool,
+        curve: VestingCurve,
+        unlock_time: i64,
+        period_unit: PeriodUnit,
     ) -> Result<()> {
         let initalize_vesting_account = &mut ctx.accounts.vesting_account;
 
-        require!(total_amount > 0, TokenVestingError::MustBeGreaterThenZero);
-        require!(
-            duration > 0 && vesting_period > 0,
-            TokenVestingError::InvalidTimestamp
-        );
-        require!(
-            duration > vesting_period,
-            TokenVestingError::VestingPeriodExceedsDuration
+        require_keys_eq!(
+            mint,
+            ctx.accounts.mint.key(),
+            TokenVestingError::MintMismatch
         );
 
-        require!(
-            duration % vesting_period == 0,
-            TokenVestingError::DurationNotDivisible
-        );
         let clock = Clock::get()?;
         let now = clock.unix_timestamp;
 
-        initalize_vesting_account.start_time = now;
-        initalize_vesting_account.mint = mint;
-        initalize_vesting_account.beneficiary = beneficiary;
-        initalize_vesting_account.vesting_period = vesting_period;
-        initalize_vesting_account.duration = duration;
-        initalize_vesting_account.total_amount = total_amount;
-        initalize_vesting_account.passed_periods = 0;
-        initalize_vesting_account.claimed_amount = 0;
-
-        // Derive PDA for token vault of contract
-        let (_vault_pda, vault_bump) = Pubkey::find_program_address(
-            &[b"vault", mint.as_ref(), &index.to_le_bytes()],
-            ctx.program_id,
-        );
-        initalize_vesting_account.vault_bump = vault_bump;
+        init_grant(
+            initalize_vesting_account,
+            ctx.accounts.user.key(),
+            mint,
+            beneficiary,
+            vesting_period,
+            duration,
+            total_amount,
+            cliff_duration,
+            start_time,
+            revocable,
+            curve,
+            unlock_time,
+            period_unit,
+            now,
+        )?;
+
+        // The token vault of the contract is derived from the vesting account
+        initalize_vesting_account.vault_bump = ctx.bumps.vault_ata;
+        initalize_vesting_account.version = TokenVesting::LAYOUT_VERSION;
 
         // Derive token vesting account PDA
         let (_vesting_acc_pda, vesting_acc_bump) = Pubkey::find_program_address(
@@ -70,71 +115,606 @@ pub mod token_vesting {
         );
         initalize_vesting_account.bump = vesting_acc_bump;
 
-        let admin_account_info = ctx.accounts.admin_ata.to_account_info();
-        let vault_info = ctx.accounts.vault_ata.to_account_info();
-        let token_program_info = ctx.accounts.token_program.to_account_info();
-        let user_account_info = ctx.accounts.user.to_account_info();
+        // Tokenized grants mint a 1-of-1 position NFT to the beneficiary, its holder owns the claim right
+        if let Some(position_mint) = &ctx.accounts.position_mint {
+            // A revocable position could be clawed back from whoever holds it
+            require!(!revocable, TokenVestingError::TokenizedPosition);
+            let position_ata = ctx
+                .accounts
+                .position_ata
+                .as_ref()
+                .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+            initalize_vesting_account.position_mint = position_mint.key();
 
-        // Transfer SPL tokens to vault
-        let cpi_ctx = CpiContext::new(
-            token_program_info,
-            SplTransfer {
-                from: admin_account_info,
-                to: vault_info,
-                authority: user_account_info,
-            },
+            let seed = [
+                b"vesting",
+                beneficiary.as_ref(),
+                &index.to_le_bytes(),
+                &[vesting_acc_bump],
+            ];
+            let signer = &[&seed[..]];
+            let token_program_info = ctx.accounts.token_program.to_account_info();
+            let vesting_account_info = ctx.accounts.vesting_account.to_account_info();
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program_info.clone(),
+                MintTo {
+                    mint: position_mint.to_account_info(),
+                    to: position_ata.to_account_info(),
+                    authority: vesting_account_info.clone(),
+                },
+                signer,
+            );
+            token_interface::mint_to(cpi_ctx, 1)?;
+
+            // Dropping the mint authority fixes the supply at one
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program_info,
+                SetAuthority {
+                    current_authority: vesting_account_info,
+                    account_or_mint: position_mint.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
+        }
+
+        // Transfer tokens to vault, so that it receives the full `total_amount`
+        let received_amount = token_utils::fund_vault(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.admin_ata.to_account_info(),
+            &ctx.accounts.mint,
+            &mut ctx.accounts.vault_ata,
+            ctx.accounts.user.to_account_info(),
+            ctx.remaining_accounts,
+            total_amount,
+        )?;
+
+        msg!(
+            "Vesting initialized, vault received {} tokens for a grant of {}",
+            received_amount,
+            total_amount
         );
-        token::transfer(cpi_ctx, total_amount)?;
+
+        let vesting_account = &ctx.accounts.vesting_account;
+        emit!(GrantCreated {
+            vesting_account: vesting_account.key(),
+            beneficiary,
+            grantor: vesting_account.grantor,
+            mint,
+            index,
+            total_amount,
+            start_time,
+            cliff_duration: vesting_account.cliff_duration,
+            vesting_period: vesting_account.vesting_period,
+            period_unit: vesting_account.period_unit,
+            duration: vesting_account.duration,
+            revocable,
+            template: Pubkey::default(),
+            timestamp: now,
+        });
 
         Ok(())
     }
 
     /// Claims vested tokens based on elapsed time since vesting started.
-    /// Tokens are linearly distributed over the duration in discrete periods.
-    pub fn claim_vested_token(ctx: Context<ClaimVestedToken>, index: u64) -> Result<()> {
+    /// Tokens are linearly distributed over the duration in discrete periods,
+    /// or continuously for `Linear` schedules.
+    pub fn claim_vested_token<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimVestedToken<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        claim_tokens(ctx, index, None)
+    }
+
+    /// Claims only `amount` of the vested but unclaimed tokens, the rest stays claimable.
+    pub fn claim_vested_amount<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimVestedToken<'info>>,
+        index: u64,
+        amount: u64,
+    ) -> Result<()> {
+        claim_tokens(ctx, index, Some(amount))
+    }
+
+    /// Revokes a revocable grant.
+    /// Settles the amount vested so far to the beneficiary and returns the unvested rest of the vault to the grantor.
+    /// No further claims can be made once a grant is revoked, except for settled tokens held back
+    /// by a lockup, which the beneficiary claims once it ends.
+    pub fn revoke_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeVesting<'info>>,
+        index: u64,
+    ) -> Result<()> {
         let vesting_account = &mut ctx.accounts.vesting_account;
         let clock = Clock::get()?;
         let now = clock.unix_timestamp;
 
-        let time_lapsed = now - vesting_account.start_time;
+        require!(vesting_account.revocable, TokenVestingError::NotRevocable);
+        require!(!vesting_account.revoked, TokenVestingError::VestingRevoked);
+
+        // Everything vested until now belongs to the beneficiary, the rest goes back to the grantor
+        let (vested_periods, vested_amount) = vesting_account.vested_at(now)?;
+        let settled_amount = math::claimable(vested_amount, vesting_account.claimed_amount)?;
+        let unvested_amount = ctx
+            .accounts
+            .vault_ata
+            .amount
+            .checked_sub(settled_amount)
+            .ok_or(TokenVestingError::NotEnoughToken)?;
+
+        // During a lockup the settled tokens stay in the vault until the beneficiary can claim them
+        let locked = vesting_account.is_locked(now);
+
+        vesting_account.passed_periods = vested_periods;
+        if !locked {
+            vesting_account.claimed_amount = vested_amount;
+        }
+        vesting_account.total_amount = vested_amount;
+        vesting_account.revoked = true;
+
+        let beneficiary_key = vesting_account.original_beneficiary;
+
+        // Create seed signer for beneficiary account
+        let seed = [
+            b"vesting",
+            beneficiary_key.as_ref(),
+            &index.to_le_bytes(),
+            &[vesting_account.bump],
+        ];
+        let signer = &[&seed[..]];
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+        let vault_info = ctx.accounts.vault_ata.to_account_info();
+        let vesting_account_info = ctx.accounts.vesting_account.to_account_info();
+
+        // Settle the vested but unclaimed tokens to the beneficiary
+        if settled_amount > 0 && !locked {
+            token_utils::transfer_checked(
+                &token_program_info,
+                vault_info.clone(),
+                &ctx.accounts.mint,
+                ctx.accounts.beneficiary_ata.to_account_info(),
+                vesting_account_info.clone(),
+                ctx.remaining_accounts,
+                settled_amount,
+                signer,
+            )?;
+        }
+
+        // Return the unvested tokens to the grantor
+        if unvested_amount > 0 {
+            token_utils::transfer_checked(
+                &token_program_info,
+                vault_info,
+                &ctx.accounts.mint,
+                ctx.accounts.grantor_ata.to_account_info(),
+                vesting_account_info,
+                ctx.remaining_accounts,
+                unvested_amount,
+                signer,
+            )?;
+        }
+
+        if locked {
+            msg!(
+                "Vesting revoked, {} settled tokens held until unlock and {} tokens returned to grantor",
+                settled_amount,
+                unvested_amount
+            );
+        } else {
+            msg!(
+                "Vesting revoked, {} tokens settled to beneficiary and {} tokens returned to grantor",
+                settled_amount,
+                unvested_amount
+            );
+        }
+
+        emit!(GrantRevoked {
+            vesting_account: ctx.accounts.vesting_account.key(),
+            beneficiary: ctx.accounts.vesting_account.beneficiary,
+            mint: ctx.accounts.mint.key(),
+            index,
+            settled_amount,
+            returned_amount: unvested_amount,
+            vested_amount,
+            passed_periods: vested_periods,
+            timestamp: now,
+        });
 
-        // Check for timelapse since contract is initiated
-        require!(time_lapsed > 0, TokenVestingError::VestingNotStarted);
+        // Without a lockup the revoke settles the grant in full
+        if !locked {
+            emit!(GrantCompleted {
+                vesting_account: ctx.accounts.vesting_account.key(),
+                beneficiary: ctx.accounts.vesting_account.beneficiary,
+                mint: ctx.accounts.mint.key(),
+                index,
+                total_amount: vested_amount,
+                passed_periods: vested_periods,
+                timestamp: now,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Closes a settled grant and returns the rent of the vesting and vault accounts to the grantor.
+    /// Allowed once the vault is empty or the grant is fully settled, any tokens left in the vault go to the grantor.
+    pub fn close_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseVesting<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let vesting_account = &ctx.accounts.vesting_account;
+        let remaining_amount = ctx.accounts.vault_ata.amount;
 
-        // Check for Vesting ended. It triggers only when both duration has ended and all tokens have been claimed by the benefiiary
         require!(
-            !(time_lapsed >= vesting_account.duration
-                && vesting_account.claimed_amount >= vesting_account.total_amount),
-            TokenVestingError::VestingEnded
+            remaining_amount == 0 || vesting_account.is_settled(),
+            TokenVestingError::VestingNotSettled
         );
 
-        let total_periods = vesting_account.duration / vesting_account.vesting_period;
-        // Calculate the amount to be claimed
-        let period_passed = time_lapsed / vesting_account.vesting_period;
+        let beneficiary_key = vesting_account.original_beneficiary;
 
-        let mut claimable_periods = period_passed - vesting_account.passed_periods;
-        if claimable_periods > total_periods {
-            claimable_periods = total_periods;
+        // Create seed signer for beneficiary account
+        let seed = [
+            b"vesting",
+            beneficiary_key.as_ref(),
+            &index.to_le_bytes(),
+            &[vesting_account.bump],
+        ];
+        let signer = &[&seed[..]];
+
+        // Sweep whatever is left in the settled vault back to the grantor
+        if remaining_amount > 0 {
+            token_utils::transfer_checked(
+                &ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.vault_ata.to_account_info(),
+                &ctx.accounts.mint,
+                ctx.accounts.grantor_ata.to_account_info(),
+                ctx.accounts.vesting_account.to_account_info(),
+                ctx.remaining_accounts,
+                remaining_amount,
+                signer,
+            )?;
         }
+
+        // Close the vault, the vesting account itself is closed by the `close` constraint
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.vault_ata.to_account_info(),
+                destination: ctx.accounts.grantor.to_account_info(),
+                authority: ctx.accounts.vesting_account.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::close_account(cpi_ctx)?;
+
+        msg!(
+            "Vesting closed, {} leftover tokens returned to grantor",
+            remaining_amount
+        );
+
+        emit!(GrantClosed {
+            vesting_account: ctx.accounts.vesting_account.key(),
+            beneficiary: ctx.accounts.vesting_account.beneficiary,
+            mint: ctx.accounts.mint.key(),
+            index,
+            claimed_amount: ctx.accounts.vesting_account.claimed_amount,
+            returned_amount: remaining_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Initializes a Merkle-distributor vesting pool.
+    /// Transfers `total_amount` of TOKENS into a single pool vault shared by every beneficiary
+    /// committed to by `merkle_root`.
+    pub fn initialize_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializePool<'info>>,
+        pool_index: u64,
+        merkle_root: [u8; 32],
+        total_amount: u64,
+    ) -> Result<()> {
+        require!(total_amount > 0, TokenVestingError::MustBeGreaterThenZero);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.grantor = ctx.accounts.grantor.key();
+        pool.mint = ctx.accounts.mint.key();
+        pool.merkle_root = merkle_root;
+        pool.total_amount = total_amount;
+        pool.claimed_amount = 0;
+        pool.pool_index = pool_index;
+        pool.bump = ctx.bumps.pool;
+        pool.vault_bump = ctx.bumps.pool_vault;
+
+        // Transfer tokens to the pool vault, charging any Token-2022 transfer fee on top
+        token_utils::fund_vault(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.grantor_ata.to_account_info(),
+            &ctx.accounts.mint,
+            &mut ctx.accounts.pool_vault,
+            ctx.accounts.grantor.to_account_info(),
+            ctx.remaining_accounts,
+            total_amount,
+        )?;
+
+        msg!("Vesting pool initialized with {} tokens", total_amount);
+
+        Ok(())
+    }
+
+    /// Claims vested tokens from a vesting pool.
+    /// The beneficiary proves their leaf `(beneficiary, total_amount, schedule)` against the pool's Merkle root,
+    /// the vested amount follows the same period logic as `claim_vested_token`.
+    pub fn claim_from_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimFromPool<'info>>,
+        pool_index: u64,
+        total_amount: u64,
+        schedule: VestingSchedule,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let beneficiary_key = ctx.accounts.beneficiary.key();
+        let pool = &mut ctx.accounts.pool;
+        let claim_status = &mut ctx.accounts.claim_status;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        let leaf = merkle::leaf_hash(&beneficiary_key, total_amount, &schedule)?;
+        require!(
+            merkle::verify(&proof, &pool.merkle_root, leaf),
+            TokenVestingError::InvalidMerkleProof
+        );
+
+        // Leaves are held to the same schedule rules as individual grants
+        validate_periods(
+            &VestingCurve::Periodic,
+            schedule.vesting_period,
+            math::duration_in_units(&schedule)?,
+        )?;
+        require!(
+            schedule.cliff_duration >= 0 && schedule.cliff_duration <= schedule.duration,
+            TokenVestingError::InvalidCliff
+        );
+
+        // The first claim creates the claim status, it announces the grant
+        let first_claim = claim_status.claimed_amount == 0;
+        claim_status.pool = pool.key();
+        claim_status.beneficiary = beneficiary_key;
+        claim_status.bump = ctx.bumps.claim_status;
+
+        require!(
+            now > schedule.start_time,
+            TokenVestingError::VestingNotStarted
+        );
+        let time_lapsed = now
+            .checked_sub(schedule.start_time)
+            .ok_or(TokenVestingError::Overflow)?;
+        require!(
+            time_lapsed >= schedule.cliff_duration,
+            TokenVestingError::CliffNotReached
+        );
+        require!(
+            claim_status.claimed_amount < total_amount,
+            TokenVestingError::VestingEnded
+        );
+
+        let (vested_periods, vested_amount) =
+            math::vested_at(&schedule, &VestingCurve::Periodic, total_amount, now)?;
+        let claimable_amount = math::claimable(vested_amount, claim_status.claimed_amount)?;
         require!(
-            claimable_periods > 0,
+            claimable_amount > 0,
             TokenVestingError::VestingPeriodNotReached
         );
 
-        // Calculate amount to be delivered per vesting period
-        let amount_per_period = vesting_account.total_amount / total_periods as u64;
+        claim_status.passed_periods = vested_periods;
+        claim_status.claimed_amount = vested_amount;
+        pool.claimed_amount = pool
+            .claimed_amount
+            .checked_add(claimable_amount)
+            .ok_or(TokenVestingError::Overflow)?;
+        require!(
+            pool.claimed_amount <= pool.total_amount,
+            TokenVestingError::OverClaimed
+        );
+
+        let grantor_key = pool.grantor;
+
+        // Create seed signer for pool account
+        let seed = [
+            b"pool",
+            grantor_key.as_ref(),
+            &pool_index.to_le_bytes(),
+            &[pool.bump],
+        ];
+        let signer = &[&seed[..]];
+
+        token_utils::transfer_checked(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.pool_vault.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.beneficiary_ata.to_account_info(),
+            ctx.accounts.pool.to_account_info(),
+            ctx.remaining_accounts,
+            claimable_amount,
+            signer,
+        )?;
 
-        // The amount beneficiary is eligible to claim on the current vesting period
-        let mut claimable_amount = amount_per_period * claimable_periods as u64;
+        let transfer_fee = token_utils::transfer_fee(&ctx.accounts.mint, claimable_amount)?;
+        msg!("Claimed {} tokens from vesting pool", claimable_amount);
 
-        // Add leftover tokens to last claim
-        if vesting_account.passed_periods + claimable_periods >= total_periods {
-            let remaining_amount = vesting_account.total_amount % total_periods as u64;
-            claimable_amount += remaining_amount;
+        let claim_status = &ctx.accounts.claim_status;
+        let mint = ctx.accounts.mint.key();
+        if first_claim {
+            emit!(GrantCreated {
+                vesting_account: claim_status.key(),
+                beneficiary: beneficiary_key,
+                grantor: grantor_key,
+                mint,
+                index: pool_index,
+                total_amount,
+                start_time: schedule.start_time,
+                cliff_duration: schedule.cliff_duration,
+                vesting_period: schedule.vesting_period,
+                period_unit: schedule.period_unit,
+                duration: schedule.duration,
+                revocable: false,
+                template: Pubkey::default(),
+                timestamp: now,
+            });
+        }
+        emit!(TokensClaimed {
+            vesting_account: claim_status.key(),
+            beneficiary: beneficiary_key,
+            mint,
+            index: pool_index,
+            amount: claimable_amount,
+            received_amount: claimable_amount - transfer_fee,
+            claimed_amount: claim_status.claimed_amount,
+            total_amount,
+            passed_periods: claim_status.passed_periods,
+            timestamp: now,
+        });
+        if claim_status.claimed_amount == total_amount {
+            emit!(GrantCompleted {
+                vesting_account: claim_status.key(),
+                beneficiary: beneficiary_key,
+                mint,
+                index: pool_index,
+                total_amount,
+                passed_periods: claim_status.passed_periods,
+                timestamp: now,
+            });
         }
-        vesting_account.passed_periods += claimable_periods;
 
-        let beneficiary_key = ctx.accounts.beneficiary.key();
+        Ok(())
+    }
+
+    /// Tops up an existing grant with `amount` more TOKENS from the grantor.
+    /// The per-period amounts are re-derived from the new `total_amount`, so periods that already
+    /// passed catch up on their share of the top-up while claimed amounts stay valid.
+    pub fn add_to_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddToVesting<'info>>,
+        index: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+
+        require!(amount > 0, TokenVestingError::MustBeGreaterThenZero);
+        require!(!vesting_account.revoked, TokenVestingError::VestingRevoked);
+
+        // Tranche amounts are fixed per unlock, they can not be re-derived from a new total
+        require!(
+            !matches!(vesting_account.curve, VestingCurve::Tranched { .. }),
+            TokenVestingError::UnsupportedSchedule
+        );
+
+        vesting_account.total_amount = vesting_account
+            .total_amount
+            .checked_add(amount)
+            .ok_or(TokenVestingError::Overflow)?;
+
+        // Transfer tokens to vault, charging any Token-2022 transfer fee on top
+        token_utils::fund_vault(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.grantor_ata.to_account_info(),
+            &ctx.accounts.mint,
+            &mut ctx.accounts.vault_ata,
+            ctx.accounts.grantor.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+        )?;
+
+        msg!(
+            "Added {} tokens to vesting, new total is {}",
+            amount,
+            ctx.accounts.vesting_account.total_amount
+        );
+
+        let vesting_account = &ctx.accounts.vesting_account;
+        emit!(GrantToppedUp {
+            vesting_account: vesting_account.key(),
+            beneficiary: vesting_account.beneficiary,
+            grantor: vesting_account.grantor,
+            mint: vesting_account.mint,
+            index,
+            amount,
+            total_amount: vesting_account.total_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the fee, in basis points of each cranked claim, paid to whoever cranks the grant
+    pub fn set_crank_fee(ctx: Context<SetCrankFee>, _index: u64, crank_fee_bps: u16) -> Result<()> {
+        require!(
+            crank_fee_bps <= MAX_CRANK_FEE_BPS,
+            TokenVestingError::CrankFeeTooHigh
+        );
+        ctx.accounts.vesting_account.crank_fee_bps = crank_fee_bps;
+
+        msg!("Crank fee set to {} bps", crank_fee_bps);
+
+        Ok(())
+    }
+
+    /// Lets the beneficiary agree to pay crankers up to `max_fee_bps` of each cranked claim.
+    /// No crank fee is charged before this is called, approving 0 withdraws the consent.
+    pub fn approve_crank_fee(
+        ctx: Context<ApproveCrankFee>,
+        _index: u64,
+        max_fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            max_fee_bps <= MAX_CRANK_FEE_BPS,
+            TokenVestingError::CrankFeeTooHigh
+        );
+        ctx.accounts.vesting_account.crank_fee_approved_bps = max_fee_bps;
+
+        msg!("Crank fee approved up to {} bps", max_fee_bps);
+
+        Ok(())
+    }
+
+    /// Permissionless claim on behalf of the beneficiary.
+    /// The vested tokens always go to the beneficiary's associated token account,
+    /// minus the crank fee which is paid to the cranker when `cranker_ata` is provided.
+    /// The fee is the grantor-set `crank_fee_bps`, capped by what the beneficiary approved.
+    /// A crank always claims everything vested so far, partial claims are left to the
+    /// beneficiary through `claim_vested_amount`.
+    pub fn crank_claim<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankClaim<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        let claimable_amount = vesting_account.record_claim(now, None)?;
+
+        // The cranker fee is carved out of the claim, only when the cranker has somewhere to receive it
+        let crank_fee_bps = vesting_account
+            .crank_fee_bps
+            .min(vesting_account.crank_fee_approved_bps);
+        let crank_fee = match ctx.accounts.cranker_ata {
+            Some(_) => {
+                let fee = (claimable_amount as u128)
+                    .checked_mul(crank_fee_bps as u128)
+                    .ok_or(TokenVestingError::Overflow)?
+                    / BPS_DENOMINATOR as u128;
+                u64::try_from(fee).map_err(|_| TokenVestingError::Overflow)?
+            }
+            None => 0,
+        };
+        let beneficiary_amount = claimable_amount
+            .checked_sub(crank_fee)
+            .ok_or(TokenVestingError::Overflow)?;
+        let received_amount = beneficiary_amount
+            .checked_sub(token_utils::transfer_fee(
+                &ctx.accounts.mint,
+                beneficiary_amount,
+            )?)
+            .ok_or(TokenVestingError::Overflow)?;
+
+        let beneficiary_key = vesting_account.original_beneficiary;
 
         // Create seed signer for beneficiary account
         let seed = [
@@ -143,107 +723,2047 @@ pub mod token_vesting {
             &index.to_le_bytes(),
             &[vesting_account.bump],
         ];
-
         let signer = &[&seed[..]];
-        let beneficiary_ata_account_info = ctx.accounts.beneficiary_ata.to_account_info();
-        let vault_info = ctx.accounts.vault_ata.to_account_info();
         let token_program_info = ctx.accounts.token_program.to_account_info();
-        let vesting_account_info = vesting_account.to_account_info();
+        let vault_info = ctx.accounts.vault_ata.to_account_info();
+        let vesting_account_info = ctx.accounts.vesting_account.to_account_info();
 
-        // Add the claimable amount to claimed account for tracking
-        vesting_account.claimed_amount = vesting_account
-            .claimed_amount
-            .checked_add(claimable_amount)
-            .ok_or(TokenVestingError::Overflow)?;
+        token_utils::transfer_checked(
+            &token_program_info,
+            vault_info.clone(),
+            &ctx.accounts.mint,
+            ctx.accounts.beneficiary_ata.to_account_info(),
+            vesting_account_info.clone(),
+            ctx.remaining_accounts,
+            beneficiary_amount,
+            signer,
+        )?;
 
-        // Check if beneficiary has overclaimed
-        require!(
-            vesting_account.claimed_amount <= vesting_account.total_amount,
-            TokenVestingError::OverClaimed
+        if let Some(cranker_ata) = &ctx.accounts.cranker_ata {
+            if crank_fee > 0 {
+                token_utils::transfer_checked(
+                    &token_program_info,
+                    vault_info,
+                    &ctx.accounts.mint,
+                    cranker_ata.to_account_info(),
+                    vesting_account_info,
+                    ctx.remaining_accounts,
+                    crank_fee,
+                    signer,
+                )?;
+            }
+        }
+
+        msg!(
+            "Cranked claim of {} tokens, {} to beneficiary and {} crank fee",
+            claimable_amount,
+            beneficiary_amount,
+            crank_fee
         );
 
-        // Transfering the claimable token from vault to beneficiary
-        let cpi_ctx = CpiContext::new_with_signer(
-            token_program_info,
-            SplTransfer {
-                from: vault_info,
-                to: beneficiary_ata_account_info,
-                authority: vesting_account_info,
-            },
-            signer,
+        emit_claim_events(
+            &ctx.accounts.vesting_account,
+            &ctx.accounts.mint.key(),
+            index,
+            claimable_amount,
+            received_amount,
+            now,
         );
 
-        token::transfer(cpi_ctx, claimable_amount)?;
+        Ok(())
+    }
+
+    /// Reassigns the beneficiary role to `new_beneficiary`, with consent of both the current beneficiary and the grantor.
+    /// The vesting PDA keeps its address, it stays derived from the original beneficiary.
+    pub fn transfer_beneficiary(
+        ctx: Context<TransferBeneficiary>,
+        index: u64,
+        new_beneficiary: Pubkey,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+
+        require_keys_neq!(
+            new_beneficiary,
+            vesting_account.beneficiary,
+            TokenVestingError::InvalidBeneficiary
+        );
+        require_keys_neq!(
+            new_beneficiary,
+            Pubkey::default(),
+            TokenVestingError::InvalidBeneficiary
+        );
+
+        let previous_beneficiary = vesting_account.beneficiary;
+        vesting_account.beneficiary = new_beneficiary;
+        // A delegate named by the previous beneficiary must not keep claiming for the new one
+        vesting_account.claim_delegate = Pubkey::default();
+        // Nor does the previous beneficiary's crank fee approval bind the new one
+        vesting_account.crank_fee_approved_bps = 0;
+
+        msg!(
+            "Beneficiary reassigned from {} to {}",
+            previous_beneficiary,
+            new_beneficiary
+        );
+
+        emit!(BeneficiaryTransferred {
+            vesting_account: vesting_account.key(),
+            previous_beneficiary,
+            new_beneficiary,
+            mint: vesting_account.mint,
+            index,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(index: u64)]
-pub struct ClaimVestedToken<'info> {
-    #[account(mut)]
-    beneficiary: Signer<'info>,
+    /// Names a delegate who may claim into the beneficiary's token account.
+    /// Passing the default pubkey removes the delegate.
+    pub fn set_claim_delegate(
+        ctx: Context<SetClaimDelegate>,
+        index: u64,
+        claim_delegate: Pubkey,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        vesting_account.claim_delegate = claim_delegate;
 
-    #[account(
-        mut,
-        seeds=[b"vesting", beneficiary.key().as_ref(), &index.to_le_bytes()],
-        bump=vesting_account.bump
-    )]
-    pub vesting_account: Account<'info, TokenVesting>,
+        msg!("Claim delegate set to {}", claim_delegate);
 
-    #[account(
-        mut,
-        seeds = [b"vault", mint.key().as_ref(), &index.to_le_bytes()],
-        bump=vesting_account.vault_bump,
-        token::mint = mint,
-        token::authority = vesting_account,
-    )]
-    pub vault_ata: Account<'info, TokenAccount>,
+        emit!(ClaimDelegateSet {
+            vesting_account: vesting_account.key(),
+            beneficiary: vesting_account.beneficiary,
+            claim_delegate,
+            mint: vesting_account.mint,
+            index,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
-    #[account(mut)]
-    pub beneficiary_ata: Account<'info, TokenAccount>,
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
+    /// Read-only view of what a grant has vested, claimed and can claim right now,
+    /// along with the next unlock time. The result is written with `set_return_data`,
+    /// so clients get exact numbers by simulating the instruction.
+    pub fn get_claimable(ctx: Context<GetClaimable>, _index: u64) -> Result<ClaimableAmount> {
+        let clock = Clock::get()?;
+        ctx.accounts
+            .vesting_account
+            .claimable_at(clock.unix_timestamp)
+    }
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+    /// Migrates a grant of an older layout version to the current layout, anyone may pay for it.
+    /// Version 1 accounts are read through their own layout and their tokens move from the legacy
+    /// `[b"vault", mint, index]` vault into the vault derived from the vesting account, the legacy
+    /// vault is closed returning its rent to the payer. Any Token-2022 transfer fee withheld on the
+    /// move is topped up from `payer_ata`.
+    pub fn migrate_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateVesting<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let vesting_info = ctx.accounts.vesting_account.to_account_info();
 
-#[derive(Accounts)]
-#[instruction(mint: Pubkey,
-        beneficiary: Pubkey,
-        vesting_period: i64,
-        duration: i64,
-        total_amount: u64,
-        index: u64)]
-pub struct InitializeVesting<'info> {
-    #[account(mut)]
-    user: Signer<'info>,
+        // Version 1 accounts end right where the version byte of later versions sits,
+        // they are the only layout older than the current one
+        {
+            let data = vesting_info.try_borrow_data()?;
+            require!(
+                data.len() >= TokenVesting::VERSION_OFFSET
+                    && data[..8] == *TokenVesting::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            require!(
+                data.len() == TokenVesting::VERSION_OFFSET,
+                TokenVestingError::AlreadyMigrated
+            );
+        }
 
-    #[account(
-        init,
-        space=8+32+32+8+8+8 +8+8+8+1+1,
-        seeds=[b"vesting", beneficiary.key().as_ref(), &index.to_le_bytes()],
-        payer = user,
-        bump
-    )]
-    pub vesting_account: Account<'info, TokenVesting>,
+        let new_len = 8 + TokenVesting::MAX_SIZE;
+        if vesting_info.data_len() < new_len {
+            let rent_due = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(vesting_info.lamports());
+            if rent_due > 0 {
+                let cpi_ctx = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: vesting_info.clone(),
+                    },
+                );
+                system_program::transfer(cpi_ctx, rent_due)?;
+            }
+            vesting_info.resize(new_len)?;
+        }
 
-    #[account(
-        init,
+        let mut vesting_account = {
+            let data = vesting_info.try_borrow_data()?;
+            TokenVesting::from_legacy(LegacyTokenVesting::deserialize(&mut &data[8..])?)
+        };
+
+        // This also proves the account is the grant's PDA
+        let beneficiary_key = vesting_account.original_beneficiary;
+        let seed = [
+            b"vesting",
+            beneficiary_key.as_ref(),
+            &index.to_le_bytes(),
+            &[vesting_account.bump],
+        ];
+        let vesting_pda = Pubkey::create_program_address(&seed, ctx.program_id)
+            .map_err(|_| ErrorCode::ConstraintSeeds)?;
+        require_keys_eq!(vesting_pda, vesting_info.key(), ErrorCode::ConstraintSeeds);
+        require_keys_eq!(
+            vesting_account.mint,
+            ctx.accounts.mint.key(),
+            TokenVestingError::MintMismatch
+        );
+
+        let mint = &ctx.accounts.mint;
+        let legacy_vault = ctx
+            .accounts
+            .legacy_vault
+            .as_ref()
+            .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+        let vault_ata = ctx
+            .accounts
+            .vault_ata
+            .as_mut()
+            .ok_or(ErrorCode::ConstraintAccountIsNone)?;
+        let signer = &[&seed[..]];
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+
+        let moved_amount = legacy_vault.amount;
+        if moved_amount > 0 {
+            token_utils::transfer_checked(
+                &token_program_info,
+                legacy_vault.to_account_info(),
+                mint,
+                vault_ata.to_account_info(),
+                vesting_info.clone(),
+                ctx.remaining_accounts,
+                moved_amount,
+                signer,
+            )?;
+        }
+
+        // The new vault has to cover everything not claimed yet
+        vault_ata.reload()?;
+        let outstanding_amount = vesting_account
+            .total_amount
+            .checked_sub(vesting_account.claimed_amount)
+            .ok_or(TokenVestingError::OverClaimed)?;
+        let shortfall = outstanding_amount.saturating_sub(vault_ata.amount);
+        if shortfall > 0 {
+            let payer_ata = ctx
+                .accounts
+                .payer_ata
+                .as_ref()
+                .ok_or(TokenVestingError::NotEnoughToken)?;
+            token_utils::fund_vault(
+                &token_program_info,
+                payer_ata.to_account_info(),
+                mint,
+                vault_ata,
+                ctx.accounts.payer.to_account_info(),
+                ctx.remaining_accounts,
+                shortfall,
+            )?;
+        }
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            token_program_info,
+            CloseAccount {
+                account: legacy_vault.to_account_info(),
+                destination: ctx.accounts.payer.to_account_info(),
+                authority: vesting_info.clone(),
+            },
+            signer,
+        );
+        token_interface::close_account(cpi_ctx)?;
+
+        vesting_account.vault_bump = ctx.bumps.vault_ata.ok_or(ErrorCode::ConstraintSeeds)?;
+
+        vesting_account.version = TokenVesting::LAYOUT_VERSION;
+        vesting_account.try_serialize(&mut &mut vesting_info.data.borrow_mut()[..])?;
+
+        msg!(
+            "Vesting migrated from layout version 1 to {}, {} tokens moved to the new vault",
+            TokenVesting::LAYOUT_VERSION,
+            moved_amount
+        );
+
+        Ok(())
+    }
+
+    /// Initializes a vesting schedule of native SOL for a beneficiary.
+    /// Transfers `total_amount` lamports to a system-owned `sol_vault` PDA, plus the rent-exempt
+    /// reserve of the vault which is returned to the grantor with the final claim.
+    /// The schedule follows the same rules as `initialize_vesting`, SOL grants are not revocable.
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_sol_vesting(
+        ctx: Context<InitializeSolVesting>,
+        beneficiary: Pubkey,
+        vesting_period: i64,
+        duration: i64,
+        total_amount: u64,
+        index: u64,
+        cliff_duration: i64,
+        start_time: i64,
+        curve: VestingCurve,
+        period_unit: PeriodUnit,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        init_grant(
+            vesting_account,
+            ctx.accounts.user.key(),
+            NATIVE_SOL_MINT,
+            beneficiary,
+            vesting_period,
+            duration,
+            total_amount,
+            cliff_duration,
+            start_time,
+            false,
+            curve,
+            0,
+            period_unit,
+            now,
+        )?;
+        vesting_account.vault_bump = ctx.bumps.sol_vault;
+        vesting_account.bump = ctx.bumps.vesting_account;
+        vesting_account.version = TokenVesting::LAYOUT_VERSION;
+
+        // The vault has to stay rent exempt while lamports are claimed out of it
+        let rent_reserve = Rent::get()?
+            .minimum_balance(0)
+            .saturating_sub(ctx.accounts.sol_vault.lamports());
+        let deposit_amount = total_amount
+            .checked_add(rent_reserve)
+            .ok_or(TokenVestingError::Overflow)?;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.sol_vault.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, deposit_amount)?;
+
+        msg!(
+            "SOL vesting initialized, vault received {} lamports and {} lamports rent reserve",
+            total_amount,
+            rent_reserve
+        );
+
+        let vesting_account = &ctx.accounts.vesting_account;
+        emit!(GrantCreated {
+            vesting_account: vesting_account.key(),
+            beneficiary,
+            grantor: vesting_account.grantor,
+            mint: NATIVE_SOL_MINT,
+            index,
+            total_amount,
+            start_time,
+            cliff_duration: vesting_account.cliff_duration,
+            vesting_period: vesting_account.vesting_period,
+            period_unit: vesting_account.period_unit,
+            duration: vesting_account.duration,
+            revocable: false,
+            template: Pubkey::default(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Claims vested lamports of a SOL grant to the beneficiary.
+    /// The final claim also returns the rent reserve of the vault to the grantor.
+    pub fn claim_vested_sol(ctx: Context<ClaimVestedSol>, index: u64) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        let claimable_amount = vesting_account.record_claim(now, None)?;
+        let settled = vesting_account.is_settled();
+
+        let vesting_key = vesting_account.key();
+
+        // Create seed signer for the SOL vault
+        let seed = [
+            b"sol_vault",
+            vesting_key.as_ref(),
+            &[vesting_account.vault_bump],
+        ];
+        let signer = &[&seed[..]];
+        let system_program_info = ctx.accounts.system_program.to_account_info();
+        let vault_info = ctx.accounts.sol_vault.to_account_info();
+
+        let cpi_context = CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Transfer {
+                from: vault_info.clone(),
+                to: ctx.accounts.beneficiary_wallet.to_account_info(),
+            },
+            signer,
+        );
+        system_program::transfer(cpi_context, claimable_amount)?;
+
+        // Once everything is claimed only the rent reserve is left, it goes back to the grantor
+        if settled {
+            let rent_reserve = vault_info.lamports();
+            if rent_reserve > 0 {
+                let cpi_context = CpiContext::new_with_signer(
+                    system_program_info,
+                    system_program::Transfer {
+                        from: vault_info,
+                        to: ctx.accounts.grantor.to_account_info(),
+                    },
+                    signer,
+                );
+                system_program::transfer(cpi_context, rent_reserve)?;
+            }
+        }
+
+        msg!("Claimed {} lamports", claimable_amount);
+
+        emit_claim_events(
+            &ctx.accounts.vesting_account,
+            &NATIVE_SOL_MINT,
+            index,
+            claimable_amount,
+            claimable_amount,
+            now,
+        );
+
+        Ok(())
+    }
+
+    /// Closes a settled SOL grant and returns the rent of the vesting account to the grantor.
+    /// Any lamports left in the vault, eg sent to it after the final claim, go to the grantor as well.
+    pub fn close_sol_vesting(ctx: Context<CloseSolVesting>, index: u64) -> Result<()> {
+        let vesting_account = &ctx.accounts.vesting_account;
+        require!(
+            vesting_account.is_settled(),
+            TokenVestingError::VestingNotSettled
+        );
+
+        let vesting_key = vesting_account.key();
+        let remaining_amount = ctx.accounts.sol_vault.lamports();
+
+        // Sweep the vault, the vesting account itself is closed by the `close` constraint
+        if remaining_amount > 0 {
+            let seed = [
+                b"sol_vault",
+                vesting_key.as_ref(),
+                &[vesting_account.vault_bump],
+            ];
+            let signer = &[&seed[..]];
+            let cpi_context = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.sol_vault.to_account_info(),
+                    to: ctx.accounts.grantor.to_account_info(),
+                },
+                signer,
+            );
+            system_program::transfer(cpi_context, remaining_amount)?;
+        }
+
+        msg!(
+            "SOL vesting closed, {} leftover lamports returned to grantor",
+            remaining_amount
+        );
+
+        emit!(GrantClosed {
+            vesting_account: vesting_key,
+            beneficiary: vesting_account.beneficiary,
+            mint: NATIVE_SOL_MINT,
+            index,
+            claimed_amount: vesting_account.claimed_amount,
+            returned_amount: remaining_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Accelerates a grant, vesting part of the unvested amount right away.
+    /// Either the amount of `count` more periods or `bps` basis points of the unvested amount.
+    /// The accelerated amount is recorded on the grant, later claims pay it out once.
+    pub fn accelerate_vesting(
+        ctx: Context<AccelerateVesting>,
+        index: u64,
+        acceleration: Acceleration,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        require!(!vesting_account.revoked, TokenVestingError::VestingRevoked);
+
+        let (vested_periods, vested_amount) = vesting_account.vested_at(now)?;
+        require!(
+            vested_amount < vesting_account.total_amount,
+            TokenVestingError::InvalidAcceleration
+        );
+
+        // Accelerations scale the amount not vested up front yet, the schedule keeps releasing the rest pro rata
+        let not_accelerated = vesting_account
+            .total_amount
+            .checked_sub(vesting_account.up_front_amount()?)
+            .ok_or(TokenVestingError::Overflow)?;
+        let (numerator, denominator) = match acceleration {
+            Acceleration::Periods { count } => {
+                let remaining_periods = vesting_account
+                    .total_periods()
+                    .checked_sub(vested_periods)
+                    .ok_or(TokenVestingError::Overflow)?;
+                require!(
+                    count > 0 && remaining_periods > 0,
+                    TokenVestingError::InvalidAcceleration
+                );
+                let remaining_periods = remaining_periods as u64;
+                ((count as u64).min(remaining_periods), remaining_periods)
+            }
+            Acceleration::Percentage { bps } => {
+                require!(
+                    bps > 0 && bps as u64 <= BPS_DENOMINATOR,
+                    TokenVestingError::InvalidAcceleration
+                );
+                (bps as u64, BPS_DENOMINATOR)
+            }
+        };
+        let amount = (not_accelerated as u128)
+            .checked_mul(numerator as u128)
+            .ok_or(TokenVestingError::Overflow)?
+            / denominator as u128;
+        let amount = u64::try_from(amount).map_err(|_| TokenVestingError::Overflow)?;
+        require!(amount > 0, TokenVestingError::InvalidAcceleration);
+
+        vesting_account.accelerated_amount = vesting_account
+            .accelerated_amount
+            .checked_add(amount)
+            .ok_or(TokenVestingError::Overflow)?;
+
+        let (_, vested_amount) = vesting_account.vested_at(now)?;
+
+        msg!(
+            "Vesting accelerated by {} tokens, {} tokens vested now",
+            amount,
+            vested_amount
+        );
+
+        emit!(GrantAccelerated {
+            vesting_account: vesting_account.key(),
+            beneficiary: vesting_account.beneficiary,
+            mint: vesting_account.mint,
+            index,
+            amount,
+            accelerated_amount: vesting_account.accelerated_amount,
+            vested_amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Splits `share_bps` of a grant off into a new grant for `new_beneficiary` with the same schedule.
+    /// Only the share of the unvested tokens moves to the new vault, everything vested so far stays
+    /// with the current beneficiary, and the two grants together vest exactly like the original.
+    /// Requires both the beneficiary and the grantor, as for reassigning the beneficiary.
+    pub fn split_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, SplitVesting<'info>>,
+        index: u64,
+        new_beneficiary: Pubkey,
+        new_index: u64,
+        share_bps: u16,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let vesting_account = &mut ctx.accounts.vesting_account;
+
+        require!(!vesting_account.revoked, TokenVestingError::VestingRevoked);
+        // Both grants are derived by scaling `total_amount`, tranches release their stored
+        // amounts instead, so scaling would need both tranche lists rewritten and re-validated
+        require!(
+            !matches!(vesting_account.curve, VestingCurve::Tranched { .. }),
+            TokenVestingError::UnsupportedSchedule
+        );
+        require!(
+            share_bps > 0 && share_bps as u64 <= BPS_DENOMINATOR,
+            TokenVestingError::InvalidSplit
+        );
+
+        let (vested_periods, vested_amount) = vesting_account.vested_at(now)?;
+        let unvested_amount = vesting_account
+            .total_amount
+            .checked_sub(vested_amount)
+            .ok_or(TokenVestingError::Overflow)?;
+        require!(unvested_amount > 0, TokenVestingError::InvalidSplit);
+
+        let share_of = |amount: u64| -> Result<u64> {
+            let share = (amount as u128)
+                .checked_mul(share_bps as u128)
+                .ok_or(TokenVestingError::Overflow)?
+                / BPS_DENOMINATOR as u128;
+            Ok(u64::try_from(share).map_err(|_| TokenVestingError::Overflow)?)
+        };
+
+        // The new grant takes its share of the whole grant, the part of it vested so far
+        // stays with the current beneficiary and counts as claimed on the new grant,
+        // it is reported as `pre_claimed_amount` of `GrantSplit`
+        let new_vesting_account = &mut ctx.accounts.new_vesting_account;
+        new_vesting_account.beneficiary = new_beneficiary;
+        new_vesting_account.original_beneficiary = new_beneficiary;
+        new_vesting_account.claim_delegate = Pubkey::default();
+        new_vesting_account.position_mint = Pubkey::default();
+        new_vesting_account.grantor = vesting_account.grantor;
+        new_vesting_account.mint = vesting_account.mint;
+        new_vesting_account.vesting_period = vesting_account.vesting_period;
+        new_vesting_account.period_unit = vesting_account.period_unit;
+        new_vesting_account.duration = vesting_account.duration;
+        new_vesting_account.curve = vesting_account.curve.clone();
+        new_vesting_account.cliff_duration = vesting_account.cliff_duration;
+        new_vesting_account.total_amount = share_of(vesting_account.total_amount)?;
+        new_vesting_account.start_time = vesting_account.start_time;
+        new_vesting_account.unlock_time = vesting_account.unlock_time;
+        new_vesting_account.accelerated_amount = share_of(vesting_account.accelerated_amount)?;
+        new_vesting_account.carried_amount = 0;
+        new_vesting_account.template = vesting_account.template;
+        new_vesting_account.revocable = vesting_account.revocable;
+        new_vesting_account.revoked = false;
+        new_vesting_account.crank_fee_bps = vesting_account.crank_fee_bps;
+        // The new beneficiary has not approved any crank fee yet
+        new_vesting_account.crank_fee_approved_bps = 0;
+        new_vesting_account.vault_bump = ctx.bumps.new_vault_ata;
+        new_vesting_account.bump = ctx.bumps.new_vesting_account;
+        new_vesting_account.version = TokenVesting::LAYOUT_VERSION;
+
+        let (_, new_vested_amount) = new_vesting_account.vested_at(now)?;
+        new_vesting_account.claimed_amount = new_vested_amount;
+        new_vesting_account.passed_periods = vested_periods;
+
+        let split_amount = new_vesting_account
+            .total_amount
+            .checked_sub(new_vested_amount)
+            .ok_or(TokenVestingError::Overflow)?;
+        require!(
+            split_amount > 0 && split_amount <= unvested_amount,
+            TokenVestingError::InvalidSplit
+        );
+
+        // The current grant gives up the split amount and keeps the rest of its acceleration,
+        // an amount carried up front keeps its vested amount unchanged
+        let remaining_total = vesting_account
+            .total_amount
+            .checked_sub(split_amount)
+            .ok_or(TokenVestingError::Overflow)?;
+        let remaining_accelerated = vesting_account
+            .accelerated_amount
+            .checked_sub(new_vesting_account.accelerated_amount)
+            .ok_or(TokenVestingError::Overflow)?;
+        let offset_up_front = vesting_account
+            .up_front_amount()?
+            .checked_sub(new_vesting_account.accelerated_amount)
+            .and_then(|amount| amount.checked_add(new_vested_amount))
+            .ok_or(TokenVestingError::Overflow)?;
+        let (_, scheduled_amount) = math::vested_at(
+            &vesting_account.schedule(),
+            &vesting_account.curve,
+            remaining_total,
+            now,
+        )?;
+        let min_up_front =
+            math::min_acceleration_for(scheduled_amount, remaining_total, vested_amount)?;
+        let up_front_amount = offset_up_front.max(min_up_front).min(remaining_total);
+        vesting_account.total_amount = remaining_total;
+        vesting_account.accelerated_amount = remaining_accelerated.min(up_front_amount);
+        vesting_account.carried_amount = up_front_amount - vesting_account.accelerated_amount;
+
+        let beneficiary_key = vesting_account.original_beneficiary;
+
+        // Create seed signer for beneficiary account
+        let seed = [
+            b"vesting",
+            beneficiary_key.as_ref(),
+            &index.to_le_bytes(),
+            &[vesting_account.bump],
+        ];
+        let signer = &[&seed[..]];
+        let token_program_info = ctx.accounts.token_program.to_account_info();
+
+        token_utils::transfer_checked(
+            &token_program_info,
+            ctx.accounts.vault_ata.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.new_vault_ata.to_account_info(),
+            ctx.accounts.vesting_account.to_account_info(),
+            ctx.remaining_accounts,
+            split_amount,
+            signer,
+        )?;
+
+        // Any Token-2022 transfer fee withheld on the move is topped up by the grantor
+        ctx.accounts.new_vault_ata.reload()?;
+        let shortfall = split_amount.saturating_sub(ctx.accounts.new_vault_ata.amount);
+        if shortfall > 0 {
+            let grantor_ata = ctx
+                .accounts
+                .grantor_ata
+                .as_ref()
+                .ok_or(TokenVestingError::NotEnoughToken)?;
+            token_utils::fund_vault(
+                &token_program_info,
+                grantor_ata.to_account_info(),
+                &ctx.accounts.mint,
+                &mut ctx.accounts.new_vault_ata,
+                ctx.accounts.grantor.to_account_info(),
+                ctx.remaining_accounts,
+                shortfall,
+            )?;
+        }
+
+        msg!(
+            "Vesting split, {} unvested tokens moved to the new grant",
+            split_amount
+        );
+
+        let vesting_account = &ctx.accounts.vesting_account;
+        let new_vesting_account = &ctx.accounts.new_vesting_account;
+        emit!(GrantSplit {
+            vesting_account: vesting_account.key(),
+            new_vesting_account: new_vesting_account.key(),
+            beneficiary: vesting_account.beneficiary,
+            new_beneficiary,
+            mint: vesting_account.mint,
+            index,
+            new_index,
+            amount: split_amount,
+            pre_claimed_amount: new_vesting_account.claimed_amount,
+            timestamp: now,
+        });
+        emit!(GrantCreated {
+            vesting_account: new_vesting_account.key(),
+            beneficiary: new_beneficiary,
+            grantor: new_vesting_account.grantor,
+            mint: new_vesting_account.mint,
+            index: new_index,
+            total_amount: new_vesting_account.total_amount,
+            start_time: new_vesting_account.start_time,
+            cliff_duration: new_vesting_account.cliff_duration,
+            vesting_period: new_vesting_account.vesting_period,
+            period_unit: new_vesting_account.period_unit,
+            duration: new_vesting_account.duration,
+            revocable: new_vesting_account.revocable,
+            template: new_vesting_account.template,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a reusable schedule template owned by the signing grantor authority.
+    /// The periods are validated like `initialize_vesting`, calendar templates count them in `period_unit`.
+    /// Tranche schedules unlock at fixed timestamps and can not be templated.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_vesting_template(
+        ctx: Context<CreateVestingTemplate>,
+        template_id: u64,
+        vesting_period: i64,
+        duration: i64,
+        cliff_duration: i64,
+        curve: VestingCurve,
+        revocable: bool,
+        period_unit: PeriodUnit,
+    ) -> Result<()> {
+        require!(
+            !matches!(curve, VestingCurve::Tranched { .. }),
+            TokenVestingError::UnsupportedSchedule
+        );
+        validate_periods(&curve, vesting_period, duration)?;
+        require!(
+            cliff_duration >= 0 && cliff_duration <= duration,
+            TokenVestingError::InvalidCliff
+        );
+
+        let template = &mut ctx.accounts.template;
+        template.authority = ctx.accounts.authority.key();
+        template.template_id = template_id;
+        template.vesting_period = vesting_period;
+        template.period_unit = period_unit;
+        template.duration = duration;
+        template.cliff_duration = cliff_duration;
+        template.curve = curve;
+        template.revocable = revocable;
+        template.bump = ctx.bumps.template;
+
+        msg!("Vesting template {} created", template_id);
+
+        Ok(())
+    }
+
+    /// Initializes a vesting schedule for a beneficiary from a template of the signing authority.
+    /// The schedule and revocability come from the template, only the amount and start are per grant.
+    /// Transfers `total_amount` of TOKENS to the vault PDA like `initialize_vesting`.
+    pub fn initialize_vesting_from_template<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeVestingFromTemplate<'info>>,
+        beneficiary: Pubkey,
+        index: u64,
+        total_amount: u64,
+        start_time: i64,
+    ) -> Result<()> {
+        let template = &ctx.accounts.template;
+        let mint = ctx.accounts.mint.key();
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        init_grant(
+            vesting_account,
+            ctx.accounts.authority.key(),
+            mint,
+            beneficiary,
+            template.vesting_period,
+            template.duration,
+            total_amount,
+            template.cliff_duration,
+            start_time,
+            template.revocable,
+            template.curve.clone(),
+            0,
+            template.period_unit,
+            now,
+        )?;
+        vesting_account.template = template.key();
+        vesting_account.vault_bump = ctx.bumps.vault_ata;
+        vesting_account.bump = ctx.bumps.vesting_account;
+        vesting_account.version = TokenVesting::LAYOUT_VERSION;
+
+        // Transfer tokens to vault, charging any Token-2022 transfer fee on top
+        token_utils::fund_vault(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.authority_ata.to_account_info(),
+            &ctx.accounts.mint,
+            &mut ctx.accounts.vault_ata,
+            ctx.accounts.authority.to_account_info(),
+            ctx.remaining_accounts,
+            total_amount,
+        )?;
+
+        msg!(
+            "Vesting initialized from template {} with {} tokens",
+            template.template_id,
+            total_amount
+        );
+
+        let vesting_account = &ctx.accounts.vesting_account;
+        emit!(GrantCreated {
+            vesting_account: vesting_account.key(),
+            beneficiary,
+            grantor: vesting_account.grantor,
+            mint,
+            index,
+            total_amount,
+            start_time,
+            cliff_duration: vesting_account.cliff_duration,
+            vesting_period: vesting_account.vesting_period,
+            period_unit: vesting_account.period_unit,
+            duration: vesting_account.duration,
+            revocable: vesting_account.revocable,
+            template: vesting_account.template,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Claims vested tokens like `claim_vested_token` and deposits them into a staking pool position
+    /// of the beneficiary in the same instruction, instead of sending them to `beneficiary_ata`.
+    /// The tokens pass through a stake escrow whose authority is a PDA of its own,
+    /// so the staking program is never handed a signature over the vault.
+    pub fn claim_and_stake<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimAndStake<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let vesting_account = &mut ctx.accounts.vesting_account;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        let claimable_amount = vesting_account.record_claim(now, None)?;
+
+        let beneficiary_key = vesting_account.original_beneficiary;
+        let seed = [
+            b"vesting",
+            beneficiary_key.as_ref(),
+            &index.to_le_bytes(),
+            &[vesting_account.bump],
+        ];
+        let signer = &[&seed[..]];
+
+        // Move the claim to the stake escrow, Token-2022 fees are withheld from it
+        token_utils::transfer_checked(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_ata.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.stake_escrow.to_account_info(),
+            ctx.accounts.vesting_account.to_account_info(),
+            ctx.remaining_accounts,
+            claimable_amount,
+            signer,
+        )?;
+        ctx.accounts.stake_escrow.reload()?;
+        let deposit_amount = ctx.accounts.stake_escrow.amount;
+
+        let vesting_key = ctx.accounts.vesting_account.key();
+        let authority_seed = [
+            b"stake_authority",
+            vesting_key.as_ref(),
+            &[ctx.bumps.stake_authority],
+        ];
+        staking::deposit(
+            staking::Deposit {
+                staking_program: ctx.accounts.staking_program.to_account_info(),
+                pool: ctx.accounts.stake_pool.to_account_info(),
+                position: ctx.accounts.stake_position.to_account_info(),
+                owner: ctx.accounts.beneficiary.to_account_info(),
+                source: ctx.accounts.stake_escrow.to_account_info(),
+                source_authority: ctx.accounts.stake_authority.to_account_info(),
+                pool_vault: ctx.accounts.stake_pool_vault.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            ctx.remaining_accounts,
+            deposit_amount,
+            &[&authority_seed[..]],
+        )?;
+
+        // Nothing may stay behind in the escrow
+        ctx.accounts.stake_escrow.reload()?;
+        require!(
+            ctx.accounts.stake_escrow.amount == 0,
+            TokenVestingError::IncompleteStakeDeposit
+        );
+
+        msg!(
+            "Claimed {} tokens, staked {} into pool {}",
+            claimable_amount,
+            deposit_amount,
+            ctx.accounts.stake_pool.key()
+        );
+
+        emit_claim_events(
+            &ctx.accounts.vesting_account,
+            &ctx.accounts.mint.key(),
+            index,
+            claimable_amount,
+            deposit_amount,
+            now,
+        );
+
+        Ok(())
+    }
+
+    /// Returns tokens the vault holds beyond what the grant still owes to the beneficiary,
+    /// eg tokens sent straight to the vault or an over-funded grant.
+    /// The unclaimed `total_amount - claimed_amount` always stays in the vault.
+    pub fn withdraw_surplus<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawSurplus<'info>>,
+        index: u64,
+    ) -> Result<()> {
+        let vesting_account = &ctx.accounts.vesting_account;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+
+        let owed_amount = vesting_account
+            .total_amount
+            .checked_sub(vesting_account.claimed_amount)
+            .ok_or(TokenVestingError::OverClaimed)?;
+        let surplus_amount = ctx.accounts.vault_ata.amount.saturating_sub(owed_amount);
+        require!(surplus_amount > 0, TokenVestingError::NoSurplus);
+
+        let beneficiary_key = vesting_account.original_beneficiary;
+        let seed = [
+            b"vesting",
+            beneficiary_key.as_ref(),
+            &index.to_le_bytes(),
+            &[vesting_account.bump],
+        ];
+        let signer = &[&seed[..]];
+
+        token_utils::transfer_checked(
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault_ata.to_account_info(),
+            &ctx.accounts.mint,
+            ctx.accounts.grantor_ata.to_account_info(),
+            ctx.accounts.vesting_account.to_account_info(),
+            ctx.remaining_accounts,
+            surplus_amount,
+            signer,
+        )?;
+
+        // The beneficiary's entitlement must still be fully backed
+        ctx.accounts.vault_ata.reload()?;
+        require!(
+            ctx.accounts.vault_ata.amount >= owed_amount,
+            TokenVestingError::NotEnoughToken
+        );
+
+        msg!("Withdrew {} surplus tokens from the vault", surplus_amount);
+
+        let vesting_account = &ctx.accounts.vesting_account;
+        emit!(SurplusWithdrawn {
+            vesting_account: vesting_account.key(),
+            grantor: vesting_account.grantor,
+            mint: vesting_account.mint,
+            index,
+            amount: surplus_amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+}
+
+/// Validates the schedule of a new grant and writes it to `vesting_account`.
+/// Shared by the token, SOL and template grants, the vault and bumps are set by the caller.
+#[allow(clippy::too_many_arguments)]
+fn init_grant(
+    vesting_account: &mut TokenVesting,
+    grantor: Pubkey,
+    mint: Pubkey,
+    beneficiary: Pubkey,
+    vesting_period: i64,
+    duration: i64,
+    total_amount: u64,
+    cliff_duration: i64,
+    start_time: i64,
+    revocable: bool,
+    curve: VestingCurve,
+    unlock_time: i64,
+    period_unit: PeriodUnit,
+    now: i64,
+) -> Result<()> {
+    require!(total_amount > 0, TokenVestingError::MustBeGreaterThenZero);
+
+    // The start can be backdated or scheduled ahead, but only within a bounded window
+    require!(
+        start_time >= now.saturating_sub(MAX_START_TIME_BACKDATE)
+            && start_time <= now.saturating_add(MAX_START_TIME_DELAY),
+        TokenVestingError::InvalidStartTime
+    );
+
+    let (vesting_period, duration, cliff_duration) = match &curve {
+        // Tranche schedules carry their own timestamps, the duration spans up to the last tranche
+        VestingCurve::Tranched { tranches } => {
+            require!(
+                period_unit == PeriodUnit::Seconds,
+                TokenVestingError::UnsupportedSchedule
+            );
+            (
+                0,
+                VestingCurve::validate_tranches(tranches, start_time, total_amount)?,
+                cliff_duration,
+            )
+        }
+        _ => {
+            validate_periods(&curve, vesting_period, duration)?;
+
+            // Calendar schedules are given in units, the duration and cliff are stored in seconds
+            if period_unit == PeriodUnit::Seconds {
+                (vesting_period, duration, cliff_duration)
+            } else {
+                (
+                    vesting_period,
+                    math::calendar_offset(start_time, period_unit, duration)?,
+                    math::calendar_offset(start_time, period_unit, cliff_duration)?,
+                )
+            }
+        }
+    };
+    require!(
+        cliff_duration >= 0 && cliff_duration <= duration,
+        TokenVestingError::InvalidCliff
+    );
+    require!(
+        unlock_time == 0 || unlock_time >= start_time,
+        TokenVestingError::InvalidUnlockTime
+    );
+
+    vesting_account.start_time = start_time;
+    vesting_account.unlock_time = unlock_time;
+    vesting_account.mint = mint;
+    vesting_account.beneficiary = beneficiary;
+    vesting_account.original_beneficiary = beneficiary;
+    vesting_account.claim_delegate = Pubkey::default();
+    vesting_account.position_mint = Pubkey::default();
+    vesting_account.grantor = grantor;
+    vesting_account.revocable = revocable;
+    vesting_account.revoked = false;
+    vesting_account.crank_fee_bps = 0;
+    vesting_account.crank_fee_approved_bps = 0;
+    vesting_account.vesting_period = vesting_period;
+    vesting_account.period_unit = period_unit;
+    vesting_account.duration = duration;
+    vesting_account.curve = curve;
+    vesting_account.cliff_duration = cliff_duration;
+    vesting_account.total_amount = total_amount;
+    vesting_account.passed_periods = 0;
+    vesting_account.claimed_amount = 0;
+    vesting_account.accelerated_amount = 0;
+    vesting_account.carried_amount = 0;
+    vesting_account.template = Pubkey::default();
+
+    Ok(())
+}
+
+/// Validates the periods of a periodic, linear or graded schedule, counted in the schedule's period unit
+fn validate_periods(curve: &VestingCurve, vesting_period: i64, duration: i64) -> Result<()> {
+    require!(
+        duration > 0 && vesting_period > 0,
+        TokenVestingError::InvalidTimestamp
+    );
+    require!(
+        duration > vesting_period,
+        TokenVestingError::VestingPeriodExceedsDuration
+    );
+
+    // Only periodic and graded schedules release whole periods, linear ones vest every second
+    require!(
+        *curve == VestingCurve::Linear || duration % vesting_period == 0,
+        TokenVestingError::DurationNotDivisible
+    );
+    if let VestingCurve::Graded { weights_bps } = curve {
+        VestingCurve::validate_weights(weights_bps, duration / vesting_period)?;
+    }
+    Ok(())
+}
+
+/// Transfers vested tokens from the vault to the beneficiary.
+/// Claims everything vested but unclaimed, or only `requested_amount` of it when provided.
+fn claim_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, ClaimVestedToken<'info>>,
+    index: u64,
+    requested_amount: Option<u64>,
+) -> Result<()> {
+    let vesting_account = &mut ctx.accounts.vesting_account;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let claimable_amount = vesting_account.record_claim(now, requested_amount)?;
+
+    let beneficiary_key = vesting_account.original_beneficiary;
+
+    // Create seed signer for beneficiary account
+    let seed = [
+        b"vesting",
+        beneficiary_key.as_ref(),
+        &index.to_le_bytes(),
+        &[vesting_account.bump],
+    ];
+
+    let signer = &[&seed[..]];
+
+    // Transfering the claimable token from vault to beneficiary, Token-2022 fees are withheld from it
+    let transfer_fee = token_utils::transfer_fee(&ctx.accounts.mint, claimable_amount)?;
+    token_utils::transfer_checked(
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.vault_ata.to_account_info(),
+        &ctx.accounts.mint,
+        ctx.accounts.beneficiary_ata.to_account_info(),
+        ctx.accounts.vesting_account.to_account_info(),
+        ctx.remaining_accounts,
+        claimable_amount,
+        signer,
+    )?;
+
+    let received_amount = claimable_amount - transfer_fee;
+    msg!(
+        "Claimed {} tokens, beneficiary received {} after {} transfer fee",
+        claimable_amount,
+        received_amount,
+        transfer_fee
+    );
+
+    emit_claim_events(
+        &ctx.accounts.vesting_account,
+        &ctx.accounts.mint.key(),
+        index,
+        claimable_amount,
+        received_amount,
+        now,
+    );
+
+    Ok(())
+}
+
+/// Emits `TokensClaimed` for a recorded claim, and `GrantCompleted` when it was the last one
+fn emit_claim_events(
+    vesting_account: &Account<TokenVesting>,
+    mint: &Pubkey,
+    index: u64,
+    amount: u64,
+    received_amount: u64,
+    now: i64,
+) {
+    emit!(TokensClaimed {
+        vesting_account: vesting_account.key(),
+        beneficiary: vesting_account.beneficiary,
+        mint: *mint,
+        index,
+        amount,
+        received_amount,
+        claimed_amount: vesting_account.claimed_amount,
+        total_amount: vesting_account.total_amount,
+        passed_periods: vesting_account.passed_periods,
+        timestamp: now,
+    });
+
+    if vesting_account.claimed_amount == vesting_account.total_amount {
+        emit!(GrantCompleted {
+            vesting_account: vesting_account.key(),
+            beneficiary: vesting_account.beneficiary,
+            mint: *mint,
+            index,
+            total_amount: vesting_account.total_amount,
+            passed_periods: vesting_account.passed_periods,
+            timestamp: now,
+        });
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct WithdrawSurplus<'info> {
+    grantor: Signer<'info>,
+
+    #[account(
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vesting_account.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ClaimAndStake<'info> {
+    /// The beneficiary, or the position holder of a tokenized grant, who owns the stake position
+    #[account(mut)]
+    beneficiary: Signer<'info>,
+
+    /// Claim delegates can not stake, the position would not belong to the beneficiary
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.can_claim(&beneficiary.key(), position_ata.as_deref()) @ TokenVestingError::UnauthorizedBeneficiary,
+        constraint = vesting_account.claim_recipient(&beneficiary.key()) == beneficiary.key() @ TokenVestingError::UnauthorizedBeneficiary
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA signing for the stake escrow only, it holds no data
+    #[account(
+        seeds = [b"stake_authority", vesting_account.key().as_ref()],
+        bump,
+    )]
+    pub stake_authority: UncheckedAccount<'info>,
+
+    /// Holds the claimed tokens while the staking pool pulls them in, empty in between claims
+    #[account(
+        init_if_needed,
+        seeds = [b"stake_escrow", vesting_account.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = stake_authority,
+        token::token_program = token_program,
+        payer = beneficiary,
+    )]
+    pub stake_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    /// The claimer's token account holding the position NFT, required for tokenized grants
+    pub position_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the staking pool program implementing `deposit`, chosen by the beneficiary
+    #[account(executable)]
+    pub staking_program: UncheckedAccount<'info>,
+
+    /// CHECK: validated by the staking program
+    #[account(mut)]
+    pub stake_pool: UncheckedAccount<'info>,
+
+    /// CHECK: the beneficiary's position in the pool, validated by the staking program
+    #[account(mut)]
+    pub stake_position: UncheckedAccount<'info>,
+
+    /// CHECK: validated by the staking program
+    #[account(mut)]
+    pub stake_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct CreateVestingTemplate<'info> {
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    #[account(
+        init,
+        space = 8 + VestingTemplate::MAX_SIZE,
+        seeds = [b"template", authority.key().as_ref(), &template_id.to_le_bytes()],
+        payer = authority,
+        bump
+    )]
+    pub template: Account<'info, VestingTemplate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(beneficiary: Pubkey, index: u64)]
+pub struct InitializeVestingFromTemplate<'info> {
+    #[account(mut)]
+    authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"template", template.authority.as_ref(), &template.template_id.to_le_bytes()],
+        bump = template.bump,
+        has_one = authority @ TokenVestingError::UnauthorizedGrantor,
+    )]
+    pub template: Account<'info, VestingTemplate>,
+
+    #[account(
+        init,
+        space=8 + TokenVesting::MAX_SIZE,
+        seeds=[b"vesting", beneficiary.as_ref(), &index.to_le_bytes()],
+        payer = authority,
+        bump
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        init,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+        payer = authority,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub authority_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64, new_beneficiary: Pubkey, new_index: u64)]
+pub struct SplitVesting<'info> {
+    beneficiary: Signer<'info>,
+
+    #[account(mut)]
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.beneficiary == beneficiary.key() @ TokenVestingError::UnauthorizedBeneficiary,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor,
+        constraint = !vesting_account.is_tokenized() @ TokenVestingError::TokenizedPosition
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        space=8 + TokenVesting::MAX_SIZE,
+        seeds=[b"vesting", new_beneficiary.as_ref(), &new_index.to_le_bytes()],
+        payer = grantor,
+        bump
+    )]
+    pub new_vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        init,
+        seeds = [b"vault", new_vesting_account.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = new_vesting_account,
+        token::token_program = token_program,
+        payer = grantor,
+    )]
+    pub new_vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Tops up any Token-2022 transfer fee withheld while moving the tokens
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = grantor,
+        token::token_program = token_program,
+    )]
+    pub grantor_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vesting_account.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct AccelerateVesting<'info> {
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+}
+
+#[derive(Accounts)]
+#[instruction(beneficiary: Pubkey, vesting_period: i64, duration: i64, total_amount: u64, index: u64)]
+pub struct InitializeSolVesting<'info> {
+    #[account(mut)]
+    user: Signer<'info>,
+
+    #[account(
+        init,
+        space=8 + TokenVesting::MAX_SIZE,
+        seeds=[b"vesting", beneficiary.key().as_ref(), &index.to_le_bytes()],
+        payer = user,
+        bump
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_vault", vesting_account.key().as_ref()],
+        bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ClaimVestedSol<'info> {
+    /// The beneficiary or their claim delegate
+    beneficiary: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.mint == NATIVE_SOL_MINT @ TokenVestingError::MintMismatch,
+        constraint = vesting_account.can_claim(&beneficiary.key(), None) @ TokenVestingError::UnauthorizedBeneficiary
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_vault", vesting_account.key().as_ref()],
+        bump = vesting_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// Claimed lamports always go to the beneficiary, also when a delegate claims
+    #[account(mut, address = vesting_account.beneficiary)]
+    pub beneficiary_wallet: SystemAccount<'info>,
+
+    /// Receives the rent reserve of the vault with the final claim
+    #[account(mut, address = vesting_account.grantor)]
+    pub grantor: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct CloseSolVesting<'info> {
+    #[account(mut)]
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        close = grantor,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.mint == NATIVE_SOL_MINT @ TokenVestingError::MintMismatch,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_vault", vesting_account.key().as_ref()],
+        bump = vesting_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct MigrateVesting<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+
+    /// CHECK: an older account does not deserialize as the current layout,
+    /// its discriminator, seeds and fields are checked by the handler
+    #[account(mut, owner = crate::ID)]
+    pub vesting_account: UncheckedAccount<'info>,
+
+    /// The `[b"vault", mint, index]` vault of a version 1 grant, later versions leave it out
+    #[account(
+        mut,
         seeds = [b"vault", mint.key().as_ref(), &index.to_le_bytes()],
         bump,
         token::mint = mint,
         token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub legacy_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault the tokens of a version 1 grant move to
+    #[account(
+        init,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+        payer = payer,
+    )]
+    pub vault_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Tops up any Token-2022 transfer fee withheld while moving the tokens
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = payer,
+        token::token_program = token_program,
+    )]
+    pub payer_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct GetClaimable<'info> {
+    #[account(
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct TransferBeneficiary<'info> {
+    beneficiary: Signer<'info>,
+
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.beneficiary == beneficiary.key() @ TokenVestingError::UnauthorizedBeneficiary,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor,
+        constraint = !vesting_account.is_tokenized() @ TokenVestingError::TokenizedPosition
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct SetClaimDelegate<'info> {
+    beneficiary: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.beneficiary == beneficiary.key() @ TokenVestingError::UnauthorizedBeneficiary,
+        constraint = !vesting_account.is_tokenized() @ TokenVestingError::TokenizedPosition
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct SetCrankFee<'info> {
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ApproveCrankFee<'info> {
+    beneficiary: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.beneficiary == beneficiary.key() @ TokenVestingError::UnauthorizedBeneficiary
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct CrankClaim<'info> {
+    cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = !vesting_account.is_tokenized() @ TokenVestingError::TokenizedPosition
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// The beneficiary's associated token account, the only place cranked tokens can go
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = vesting_account.beneficiary,
+        associated_token::token_program = token_program,
+    )]
+    pub beneficiary_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = cranker,
+        token::token_program = token_program,
+    )]
+    pub cranker_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = vesting_account.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct AddToVesting<'info> {
+    #[account(mut)]
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vesting_account.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_index: u64)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    grantor: Signer<'info>,
+
+    #[account(
+        init,
+        space = 8 + VestingPool::MAX_SIZE,
+        seeds = [b"pool", grantor.key().as_ref(), &pool_index.to_le_bytes()],
+        payer = grantor,
+        bump
+    )]
+    pub pool: Account<'info, VestingPool>,
+
+    #[account(
+        init,
+        seeds = [b"pool_vault", pool.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        payer = grantor,
+    )]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_index: u64)]
+pub struct ClaimFromPool<'info> {
+    #[account(mut)]
+    beneficiary: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", pool.grantor.as_ref(), &pool_index.to_le_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, VestingPool>,
+
+    #[account(
+        init_if_needed,
+        space = 8 + ClaimStatus::MAX_SIZE,
+        seeds = [b"claim_status", pool.key().as_ref(), beneficiary.key().as_ref()],
+        payer = beneficiary,
+        bump
+    )]
+    pub claim_status: Account<'info, ClaimStatus>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault", pool.key().as_ref()],
+        bump = pool.vault_bump,
+        token::mint = mint,
+        token::authority = pool,
+        token::token_program = token_program,
+    )]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = beneficiary,
+        token::token_program = token_program,
+    )]
+    pub beneficiary_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct CloseVesting<'info> {
+    #[account(mut)]
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        close = grantor,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vesting_account.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct RevokeVesting<'info> {
+    #[account(mut)]
+    grantor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.grantor == grantor.key() @ TokenVestingError::UnauthorizedGrantor
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = vesting_account.beneficiary,
+        token::token_program = token_program,
+    )]
+    pub beneficiary_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vesting_account.mint @ TokenVestingError::MintMismatch)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ClaimVestedToken<'info> {
+    /// The beneficiary or their claim delegate
+    #[account(mut)]
+    beneficiary: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[b"vesting", vesting_account.original_beneficiary.as_ref(), &index.to_le_bytes()],
+        bump=vesting_account.bump,
+        constraint = vesting_account.version == TokenVesting::LAYOUT_VERSION @ TokenVestingError::OutdatedLayout,
+        constraint = vesting_account.can_claim(&beneficiary.key(), position_ata.as_deref()) @ TokenVestingError::UnauthorizedBeneficiary
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump=vesting_account.vault_bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Claimed tokens always go to a token account of the beneficiary, also when a delegate claims.
+    /// For tokenized grants they go to a token account of the position holder.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+        constraint = beneficiary_ata.owner == vesting_account.claim_recipient(&beneficiary.key()) @ TokenVestingError::UnauthorizedBeneficiary,
+    )]
+    pub beneficiary_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// The claimer's token account holding the position NFT, required for tokenized grants
+    pub position_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey,
+        beneficiary: Pubkey,
+        vesting_period: i64,
+        duration: i64,
+        total_amount: u64,
+        index: u64,
+        cliff_duration: i64,
+        start_time: i64,
+        revocable: bool,
+        curve: VestingCurve,
+        unlock_time: i64,
+        period_unit: PeriodUnit)]
+pub struct InitializeVesting<'info> {
+    #[account(mut)]
+    user: Signer<'info>,
+
+    #[account(
+        init,
+        space=8 + TokenVesting::MAX_SIZE,
+        seeds=[b"vesting", beneficiary.key().as_ref(), &index.to_le_bytes()],
+        payer = user,
+        bump
+    )]
+    pub vesting_account: Account<'info, TokenVesting>,
+
+    #[account(
+        init,
+        seeds = [b"vault", vesting_account.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vesting_account,
+        token::token_program = token_program,
         payer = user,
     )]
-    pub vault_ata: Account<'info, TokenAccount>,
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub admin_ata: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+    pub admin_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Position NFT of a tokenized grant, leave out for a grant claimed by the beneficiary key
+    #[account(
+        init,
+        seeds = [b"position", vesting_account.key().as_ref()],
+        bump,
+        payer = user,
+        mint::decimals = 0,
+        mint::authority = vesting_account,
+        mint::token_program = token_program,
+    )]
+    pub position_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: the beneficiary wallet the position NFT is minted to
+    #[account(address = beneficiary)]
+    pub position_owner: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = position_mint,
+        associated_token::authority = position_owner,
+        associated_token::token_program = token_program,
+    )]
+    pub position_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
     pub system_program: Program<'info, System>,
 }
@@ -0,0 +1,369 @@
+use anchor_lang::prelude::*;
+
+use crate::calendar;
+use crate::error::TokenVestingError;
+use crate::state::{PeriodUnit, VestingCurve, VestingSchedule, WEIGHTS_BPS_TOTAL};
+
+/// Total number of vesting periods in the schedule
+pub fn total_periods(schedule: &VestingSchedule, curve: &VestingCurve) -> i64 {
+    match curve {
+        VestingCurve::Tranched { tranches } => tranches.len() as i64,
+        _ => match months_per_period(schedule) {
+            Some(period_months) => schedule
+                .start_time
+                .checked_add(schedule.duration)
+                .and_then(|end_time| calendar::months_between(schedule.start_time, end_time))
+                .map_or(0, |months| months / period_months),
+            None => schedule.duration / schedule.vesting_period,
+        },
+    }
+}
+
+/// Calendar months in one vesting period, `None` for fixed-second periods
+fn months_per_period(schedule: &VestingSchedule) -> Option<i64> {
+    schedule
+        .period_unit
+        .months()
+        .map(|unit_months| unit_months.saturating_mul(schedule.vesting_period).max(1))
+}
+
+/// Seconds from `start_time` to `units` calendar units later, month ends are clamped
+pub fn calendar_offset(start_time: i64, period_unit: PeriodUnit, units: i64) -> Result<i64> {
+    let months = period_unit
+        .months()
+        .and_then(|unit_months| unit_months.checked_mul(units))
+        .ok_or(TokenVestingError::Overflow)?;
+    calendar::add_months(start_time, months)
+        .and_then(|end_time| end_time.checked_sub(start_time))
+        .ok_or(TokenVestingError::Overflow.into())
+}
+
+/// The duration of the schedule counted in its period unit.
+/// Calendar schedules must end exactly a whole number of units after the start.
+pub fn duration_in_units(schedule: &VestingSchedule) -> Result<i64> {
+    let Some(unit_months) = schedule.period_unit.months() else {
+        return Ok(schedule.duration);
+    };
+    let end_time = schedule
+        .start_time
+        .checked_add(schedule.duration)
+        .ok_or(TokenVestingError::Overflow)?;
+    let months = calendar::months_between(schedule.start_time, end_time)
+        .ok_or(TokenVestingError::Overflow)?;
+    require!(
+        months % unit_months == 0
+            && calendar::add_months(schedule.start_time, months) == Some(end_time),
+        TokenVestingError::DurationNotDivisible
+    );
+    Ok(months / unit_months)
+}
+
+/// Number of whole vesting periods elapsed from the start of the schedule to `now`
+fn elapsed_periods(schedule: &VestingSchedule, now: i64) -> Result<i64> {
+    let time_lapsed = now.saturating_sub(schedule.start_time).max(0);
+    match months_per_period(schedule) {
+        Some(period_months) => Ok(calendar::months_between(schedule.start_time, now)
+            .ok_or(TokenVestingError::Overflow)?
+            / period_months),
+        None => Ok(time_lapsed / schedule.vesting_period),
+    }
+}
+
+/// Timestamp at which the `period`-th vesting period ends
+fn period_end(schedule: &VestingSchedule, period: i64) -> Result<i64> {
+    let end_time = match months_per_period(schedule) {
+        Some(period_months) => period
+            .checked_mul(period_months)
+            .and_then(|months| calendar::add_months(schedule.start_time, months)),
+        None => period
+            .checked_mul(schedule.vesting_period)
+            .and_then(|offset| schedule.start_time.checked_add(offset)),
+    };
+    end_time.ok_or(TokenVestingError::Overflow.into())
+}
+
+/// Returns the number of periods vested at `now` and the amount vested by then.
+/// Nothing is vested before the cliff, leftover tokens are added to the last period.
+/// Linear schedules vest every second, so the amount is not limited to whole periods.
+/// Calendar periods end on the same day of the month as the start, clamped to the end of shorter months.
+/// Tranche schedules release every tranche whose unlock timestamp has passed.
+pub fn vested_at(
+    schedule: &VestingSchedule,
+    curve: &VestingCurve,
+    total_amount: u64,
+    now: i64,
+) -> Result<(i64, u64)> {
+    if now <= schedule.start_time {
+        return Ok((0, 0));
+    }
+    let time_lapsed = now
+        .checked_sub(schedule.start_time)
+        .ok_or(TokenVestingError::Overflow)?;
+    if time_lapsed < schedule.cliff_duration {
+        return Ok((0, 0));
+    }
+
+    let total_periods = total_periods(schedule, curve);
+    if time_lapsed >= schedule.duration {
+        return Ok((total_periods, total_amount));
+    }
+
+    let (vested_periods, vested_amount) = match curve {
+        VestingCurve::Periodic => {
+            let vested_periods = elapsed_periods(schedule, now)?.min(total_periods);
+            // Calculate amount to be delivered per vesting period
+            let amount_per_period = total_amount
+                .checked_div(total_periods as u64)
+                .ok_or(TokenVestingError::Overflow)?;
+            let vested_amount = amount_per_period
+                .checked_mul(vested_periods as u64)
+                .ok_or(TokenVestingError::Overflow)?;
+            (vested_periods, vested_amount)
+        }
+        VestingCurve::Linear => {
+            let vested_periods = elapsed_periods(schedule, now)?.min(total_periods);
+            // Everything elapsed so far is vested, computed in u128 to avoid overflow
+            let vested = (total_amount as u128)
+                .checked_mul(time_lapsed as u128)
+                .ok_or(TokenVestingError::Overflow)?
+                / schedule.duration as u128;
+            let vested_amount = u64::try_from(vested).map_err(|_| TokenVestingError::Overflow)?;
+            (vested_periods, vested_amount)
+        }
+        VestingCurve::Graded { weights_bps } => {
+            let vested_periods = elapsed_periods(schedule, now)?.min(total_periods);
+            // Each period is rounded down on its own, the dust is released with the final period
+            let mut vested_amount: u64 = 0;
+            for &weight in weights_bps.iter().take(vested_periods as usize) {
+                let period_amount = (total_amount as u128)
+                    .checked_mul(weight as u128)
+                    .ok_or(TokenVestingError::Overflow)?
+                    / WEIGHTS_BPS_TOTAL as u128;
+                vested_amount = vested_amount
+                    .checked_add(period_amount as u64)
+                    .ok_or(TokenVestingError::Overflow)?;
+            }
+            (vested_periods, vested_amount)
+        }
+        VestingCurve::Tranched { tranches } => {
+            // Every tranche whose time has passed is released
+            let mut vested_periods = 0;
+            let mut vested_amount: u64 = 0;
+            for tranche in tranches.iter().take_while(|t| t.unlock_timestamp <= now) {
+                vested_periods += 1;
+                vested_amount = vested_amount
+                    .checked_add(tranche.amount)
+                    .ok_or(TokenVestingError::Overflow)?;
+            }
+            (vested_periods, vested_amount)
+        }
+    };
+
+    Ok((vested_periods, vested_amount))
+}
+
+/// Applies grantor accelerations to the amount vested by the schedule.
+/// `accelerated_amount` is vested up front and the schedule releases the rest of `total_amount`
+/// pro rata, so the vested amount never drops and still reaches `total_amount` at the end.
+pub fn with_acceleration(
+    scheduled_amount: u64,
+    total_amount: u64,
+    accelerated_amount: u64,
+) -> Result<u64> {
+    if accelerated_amount == 0 || total_amount == 0 {
+        return Ok(scheduled_amount);
+    }
+    let remaining_amount = total_amount
+        .checked_sub(accelerated_amount)
+        .ok_or(TokenVestingError::Overflow)?;
+    let scheduled_share = (scheduled_amount as u128)
+        .checked_mul(remaining_amount as u128)
+        .ok_or(TokenVestingError::Overflow)?
+        / total_amount as u128;
+    Ok(accelerated_amount
+        .checked_add(scheduled_share as u64)
+        .ok_or(TokenVestingError::Overflow)?)
+}
+
+/// Returns the smallest accelerated amount for which `with_acceleration` of `scheduled_amount`
+/// reaches `target_amount`, `X + S * (T - X) / T >= V` solved for `X`.
+pub fn min_acceleration_for(
+    scheduled_amount: u64,
+    total_amount: u64,
+    target_amount: u64,
+) -> Result<u64> {
+    if target_amount <= scheduled_amount || scheduled_amount >= total_amount {
+        return Ok(0);
+    }
+    let numerator = (total_amount as u128)
+        .checked_mul((target_amount - scheduled_amount) as u128)
+        .ok_or(TokenVestingError::Overflow)?;
+    let denominator = (total_amount - scheduled_amount) as u128;
+    let min_accelerated = numerator.div_ceil(denominator);
+    u64::try_from(min_accelerated).map_err(|_| TokenVestingError::Overflow.into())
+}
+
+/// The amount vested but not yet claimed
+pub fn claimable(vested_amount: u64, claimed_amount: u64) -> Result<u64> {
+    Ok(vested_amount
+        .checked_sub(claimed_amount)
+        .ok_or(TokenVestingError::OverClaimed)?)
+}
+
+/// Returns the next timestamp after `now` at which more tokens vest,
+/// or `None` once the schedule is fully vested.
+pub fn next_unlock_time(
+    schedule: &VestingSchedule,
+    curve: &VestingCurve,
+    now: i64,
+) -> Result<Option<i64>> {
+    let end_time = schedule
+        .start_time
+        .checked_add(schedule.duration)
+        .ok_or(TokenVestingError::Overflow)?;
+    if now >= end_time {
+        return Ok(None);
+    }
+
+    // Nothing vests at the start itself nor before the cliff
+    let first_unlock = schedule
+        .start_time
+        .checked_add(schedule.cliff_duration.max(1))
+        .ok_or(TokenVestingError::Overflow)?;
+    let candidate = match curve {
+        VestingCurve::Periodic => period_end(schedule, elapsed_periods(schedule, now)? + 1)?,
+        VestingCurve::Graded { weights_bps } => {
+            // Periods weighted zero release nothing, the next unlock is the next weighted period
+            let next_period = elapsed_periods(schedule, now)? as usize;
+            let weighted_period = weights_bps
+                .iter()
+                .enumerate()
+                .skip(next_period)
+                .find(|(_, &weight)| weight > 0)
+                .map_or(weights_bps.len(), |(i, _)| i);
+            period_end(schedule, weighted_period as i64 + 1)?
+        }
+        VestingCurve::Linear => now.checked_add(1).ok_or(TokenVestingError::Overflow)?,
+        VestingCurve::Tranched { tranches } => tranches
+            .iter()
+            .map(|t| t.unlock_timestamp)
+            .find(|&t| t > now)
+            .unwrap_or(end_time),
+    };
+
+    Ok(Some(candidate.max(first_unlock).min(end_time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn monthly_schedule(start_time: i64, months: i64) -> VestingSchedule {
+        VestingSchedule {
+            start_time,
+            cliff_duration: 0,
+            vesting_period: 1,
+            period_unit: PeriodUnit::Months,
+            duration: calendar_offset(start_time, PeriodUnit::Months, months).unwrap(),
+        }
+    }
+
+    #[test]
+    fn monthly_periods_end_on_month_ends() {
+        // 2024-01-31, a leap year
+        let start_time = calendar::days_from_civil(2024, 1, 31) * DAY;
+        let schedule = monthly_schedule(start_time, 12);
+        let curve = VestingCurve::Periodic;
+        assert_eq!(total_periods(&schedule, &curve), 12);
+        assert_eq!(
+            schedule.start_time + schedule.duration,
+            calendar::days_from_civil(2025, 1, 31) * DAY
+        );
+
+        let feb_29 = calendar::days_from_civil(2024, 2, 29) * DAY;
+        assert_eq!(
+            vested_at(&schedule, &curve, 1200, feb_29 - 1).unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            vested_at(&schedule, &curve, 1200, feb_29).unwrap(),
+            (1, 100)
+        );
+        let apr_30 = calendar::days_from_civil(2024, 4, 30) * DAY;
+        assert_eq!(
+            vested_at(&schedule, &curve, 1200, apr_30).unwrap(),
+            (3, 300)
+        );
+
+        assert_eq!(
+            next_unlock_time(&schedule, &curve, start_time).unwrap(),
+            Some(feb_29)
+        );
+        assert_eq!(
+            next_unlock_time(&schedule, &curve, feb_29).unwrap(),
+            Some(calendar::days_from_civil(2024, 3, 31) * DAY)
+        );
+    }
+
+    #[test]
+    fn quarterly_and_yearly_periods() {
+        let start_time = calendar::days_from_civil(2024, 2, 29) * DAY;
+        let quarterly = VestingSchedule {
+            vesting_period: 1,
+            period_unit: PeriodUnit::Quarters,
+            ..monthly_schedule(start_time, 12)
+        };
+        assert_eq!(total_periods(&quarterly, &VestingCurve::Periodic), 4);
+
+        let yearly = VestingSchedule {
+            vesting_period: 1,
+            period_unit: PeriodUnit::Years,
+            duration: calendar_offset(start_time, PeriodUnit::Years, 4).unwrap(),
+            ..quarterly
+        };
+        assert_eq!(total_periods(&yearly, &VestingCurve::Periodic), 4);
+        // The first anniversary of a leap day falls on Feb 28
+        let feb_28 = calendar::days_from_civil(2025, 2, 28) * DAY;
+        assert_eq!(
+            vested_at(&yearly, &VestingCurve::Periodic, 400, feb_28).unwrap(),
+            (1, 100)
+        );
+        assert_eq!(
+            vested_at(&yearly, &VestingCurve::Periodic, 400, feb_28 - 1).unwrap(),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn calendar_duration_in_units() {
+        let start_time = calendar::days_from_civil(2024, 1, 31) * DAY;
+        let schedule = monthly_schedule(start_time, 12);
+        assert_eq!(duration_in_units(&schedule).unwrap(), 12);
+
+        let quarterly = VestingSchedule {
+            period_unit: PeriodUnit::Quarters,
+            ..schedule
+        };
+        assert_eq!(duration_in_units(&quarterly).unwrap(), 4);
+
+        // A duration off the month boundary, or not a whole number of quarters, is rejected
+        let off_boundary = VestingSchedule {
+            duration: schedule.duration + DAY,
+            ..schedule
+        };
+        assert!(duration_in_units(&off_boundary).is_err());
+        let partial_quarter = VestingSchedule {
+            duration: calendar_offset(start_time, PeriodUnit::Months, 13).unwrap(),
+            ..quarterly
+        };
+        assert!(duration_in_units(&partial_quarter).is_err());
+
+        let seconds = VestingSchedule {
+            period_unit: PeriodUnit::Seconds,
+            ..schedule
+        };
+        assert_eq!(duration_in_units(&seconds).unwrap(), schedule.duration);
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::state::VestingSchedule;
+
+/// Domain separators so that a leaf can never be passed off as an inner node
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hashes a vesting pool leaf committing to `(beneficiary, total_amount, schedule)`
+pub fn leaf_hash(
+    beneficiary: &Pubkey,
+    total_amount: u64,
+    schedule: &VestingSchedule,
+) -> Result<[u8; 32]> {
+    let schedule_bytes = schedule.try_to_vec()?;
+    Ok(hashv(&[
+        LEAF_PREFIX,
+        beneficiary.as_ref(),
+        &total_amount.to_le_bytes(),
+        &schedule_bytes,
+    ])
+    .to_bytes())
+}
+
+/// Verifies a Merkle proof, sibling pairs are hashed in sorted order
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[NODE_PREFIX, &node, sibling]).to_bytes()
+        } else {
+            hashv(&[NODE_PREFIX, sibling, &node]).to_bytes()
+        }
+    });
+    computed == *root
+}
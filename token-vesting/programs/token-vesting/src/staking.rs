@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+/// Discriminator of the pool's `deposit` instruction, the first 8 bytes of `sha256("global:deposit")`
+pub const DEPOSIT_DISCRIMINATOR: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+
+/// Accounts of the `deposit(amount: u64)` instruction a staking pool has to implement.
+/// The pool pulls `amount` of `mint` out of `source`, signed by `source_authority`,
+/// into `pool_vault` and credits it to the `position` of `owner`.
+pub struct Deposit<'info> {
+    pub staking_program: AccountInfo<'info>,
+    pub pool: AccountInfo<'info>,
+    pub position: AccountInfo<'info>,
+    pub owner: AccountInfo<'info>,
+    pub source: AccountInfo<'info>,
+    pub source_authority: AccountInfo<'info>,
+    pub pool_vault: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+/// Calls `deposit` on the staking pool.
+/// Any extra accounts the pool needs, eg for transfer hooks, are forwarded after the fixed ones.
+pub fn deposit<'info>(
+    accounts: Deposit<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut metas = vec![
+        AccountMeta::new(accounts.pool.key(), false),
+        AccountMeta::new(accounts.position.key(), false),
+        AccountMeta::new_readonly(accounts.owner.key(), true),
+        AccountMeta::new(accounts.source.key(), false),
+        AccountMeta::new_readonly(accounts.source_authority.key(), true),
+        AccountMeta::new(accounts.pool_vault.key(), false),
+        AccountMeta::new_readonly(accounts.mint.key(), false),
+        AccountMeta::new_readonly(accounts.token_program.key(), false),
+    ];
+    metas.extend(remaining_accounts.iter().map(|account| AccountMeta {
+        pubkey: account.key(),
+        is_signer: false,
+        is_writable: account.is_writable,
+    }));
+
+    let mut data = DEPOSIT_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: accounts.staking_program.key(),
+        accounts: metas,
+        data,
+    };
+
+    let mut infos = vec![
+        accounts.pool,
+        accounts.position,
+        accounts.owner,
+        accounts.source,
+        accounts.source_authority,
+        accounts.pool_vault,
+        accounts.mint,
+        accounts.token_program,
+        accounts.staking_program,
+    ];
+    infos.extend_from_slice(remaining_accounts);
+
+    invoke_signed(&instruction, &infos, signer_seeds)?;
+    Ok(())
+}
@@ -1,4 +1,144 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::error::TokenVestingError;
+use crate::math;
+
+/// Maximum number of tranches a single vesting schedule can hold
+pub const MAX_TRANCHES: usize = 24;
+
+/// Maximum number of periods a graded vesting schedule can weight
+pub const MAX_GRADED_PERIODS: usize = 48;
+
+/// Basis points in 100%, the sum of the weights of a graded schedule
+pub const WEIGHTS_BPS_TOTAL: u64 = 10_000;
+
+/// A single unlock of an irregular vesting schedule
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tranche {
+    /// Timestamp after which the tranche can be claimed
+    pub unlock_timestamp: i64,
+
+    /// The amount of tokens released by the tranche
+    pub amount: u64,
+}
+
+impl Tranche {
+    pub const SIZE: usize = 8 + 8;
+}
+
+/// The shape of the vesting schedule
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum VestingCurve {
+    /// Tokens unlock in equal chunks at the end of every vesting period
+    Periodic,
+
+    /// Tokens unlock continuously every second, `total_amount * elapsed / duration`
+    Linear,
+
+    /// Tokens unlock in irregular tranches, each at its own timestamp
+    Tranched { tranches: Vec<Tranche> },
+
+    /// Tokens unlock at the end of every vesting period like `Periodic`,
+    /// but each period releases its own share of `total_amount` in basis points
+    Graded { weights_bps: Vec<u16> },
+}
+
+impl VestingCurve {
+    /// Sized for the largest variant, a full tranche list outweighs a full weight list
+    pub const MAX_SIZE: usize = 1 + 4 + MAX_TRANCHES * Tranche::SIZE;
+
+    /// Validates a tranche schedule and returns the duration it spans from `start_time`.
+    /// Timestamps must be strictly increasing, not before `start_time`,
+    /// and the tranche amounts must add up to `total_amount`.
+    pub fn validate_tranches(
+        tranches: &[Tranche],
+        start_time: i64,
+        total_amount: u64,
+    ) -> Result<i64> {
+        require!(
+            !tranches.is_empty() && tranches.len() <= MAX_TRANCHES,
+            TokenVestingError::InvalidTrancheSchedule
+        );
+
+        let mut previous_timestamp = start_time;
+        let mut tranche_sum: u64 = 0;
+        for (i, tranche) in tranches.iter().enumerate() {
+            // The first tranche may unlock right at the start (eg, at TGE)
+            require!(
+                tranche.unlock_timestamp > previous_timestamp
+                    || (i == 0 && tranche.unlock_timestamp == start_time),
+                TokenVestingError::InvalidTrancheSchedule
+            );
+            previous_timestamp = tranche.unlock_timestamp;
+            tranche_sum = tranche_sum
+                .checked_add(tranche.amount)
+                .ok_or(TokenVestingError::Overflow)?;
+        }
+        require!(
+            tranche_sum == total_amount,
+            TokenVestingError::TrancheAmountMismatch
+        );
+
+        previous_timestamp
+            .checked_sub(start_time)
+            .ok_or(TokenVestingError::Overflow.into())
+    }
+
+    /// Validates the weights of a graded schedule.
+    /// There must be one weight per period and the weights must add up to 10_000 basis points.
+    pub fn validate_weights(weights_bps: &[u16], total_periods: i64) -> Result<()> {
+        require!(
+            weights_bps.len() <= MAX_GRADED_PERIODS && weights_bps.len() as i64 == total_periods,
+            TokenVestingError::InvalidWeights
+        );
+        let weights_sum: u64 = weights_bps.iter().map(|&w| w as u64).sum();
+        require!(
+            weights_sum == WEIGHTS_BPS_TOTAL,
+            TokenVestingError::InvalidWeights
+        );
+        Ok(())
+    }
+}
+
+/// The unit the vesting period is counted in
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PeriodUnit {
+    /// Fixed periods of `vesting_period` seconds
+    #[default]
+    Seconds,
+
+    /// Calendar months, a period ends on the same day of the month as the start (or the last day of shorter months)
+    Months,
+
+    /// Calendar quarters of three months
+    Quarters,
+
+    /// Calendar years of twelve months
+    Years,
+}
+
+impl PeriodUnit {
+    /// Number of calendar months in one unit, `None` for fixed-second periods
+    pub fn months(&self) -> Option<i64> {
+        match self {
+            PeriodUnit::Seconds => None,
+            PeriodUnit::Months => Some(1),
+            PeriodUnit::Quarters => Some(3),
+            PeriodUnit::Years => Some(12),
+        }
+    }
+}
+
+/// How much of the unvested amount an acceleration vests right away
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Acceleration {
+    /// Vests the amount of `count` more periods, spread evenly over the periods still ahead
+    Periods { count: u16 },
+
+    /// Vests `bps` basis points of the amount not vested yet
+    Percentage { bps: u16 },
+}
 
 /// Represents a token vesting account.
 ///
@@ -11,24 +151,28 @@ pub struct TokenVesting {
     // /// This account should be controlled programatically and must not be accessed manually
     // pub owner_vault: Pubkey,
     /// The beneficiary who will receive the vested tokens.
+    /// Can be reassigned with consent of both the beneficiary and the grantor.
     pub beneficiary: Pubkey,
 
     /// The SPL token mint for the token being vested
     pub mint: Pubkey,
 
-    /// The vesting period in seconds.
+    /// The vesting period in seconds, or in `period_unit` for calendar periods.
     /// Defines how frequently tokens become claimable (eg, Every 4 months)
+    /// Unused by tranche schedules, where every tranche counts as one period
     pub vesting_period: i64,
 
     /// The duration of the vesting schedule in seconds.
     /// Determines how long it will take for all tokens to fully vest.
+    /// For tranche schedules it spans from the start to the last tranche.
     pub duration: i64,
 
     /// The total amount of tokens to be vested to the beneficiary
     /// Tokens will be linearly released over the duration based on the vesting period.
     pub total_amount: u64,
 
-    /// Starting timestamp of vesting for a beneficiary
+    /// Starting timestamp of vesting for a beneficiary.
+    /// Provided at initialization, it can be backdated or lie in the future
     pub start_time: i64,
 
     /// The amount the beneficiary has claimed so far
@@ -42,4 +186,477 @@ pub struct TokenVesting {
 
     /// The bump seed for the vesting account that stores program state
     pub bump: u8,
+
+    // Every field above is laid out as in version 1 accounts, new fields are only appended below
+    /// Layout version of the account.
+    /// Version 1 accounts predate this byte and derive their vault from `[b"vault", mint, index]`,
+    /// version 2 derives the vault from the vesting account key.
+    pub version: u8,
+
+    /// The beneficiary the vesting PDA was derived from.
+    /// Stays fixed when the beneficiary role is reassigned, so PDA lookups keep working.
+    pub original_beneficiary: Pubkey,
+
+    /// Optional key allowed to claim into the beneficiary's token account, default pubkey when unset
+    pub claim_delegate: Pubkey,
+
+    /// Mint of the 1-of-1 position NFT of a tokenized grant, default pubkey when not tokenized.
+    /// Whoever holds the NFT owns the right to claim, instead of `beneficiary`.
+    pub position_mint: Pubkey,
+
+    /// The grantor who funded the vesting and may revoke it when `revocable` is set
+    pub grantor: Pubkey,
+
+    /// The template the grant was created from, default pubkey when it was not
+    pub template: Pubkey,
+
+    /// The cliff duration in seconds, counted from the start of vesting.
+    /// No tokens can be claimed before the cliff, at the cliff every period accrued so far unlocks at once.
+    pub cliff_duration: i64,
+
+    /// End of an optional lockup, 0 when there is none.
+    /// Tokens keep vesting on schedule but can not leave the vault before this timestamp.
+    pub unlock_time: i64,
+
+    /// The amount vested ahead of the schedule by grantor accelerations.
+    /// The schedule releases the rest of `total_amount` on top of it.
+    pub accelerated_amount: u64,
+
+    /// The amount vested up front to keep the vested amount unchanged when part of the unvested
+    /// tokens was split off to another grant. Vests like `accelerated_amount`, but is no acceleration.
+    pub carried_amount: u64,
+
+    /// Whether the grantor is allowed to revoke the grant and claw back unvested tokens
+    pub revocable: bool,
+
+    /// Set once the grant has been revoked, no claims are accepted afterwards
+    pub revoked: bool,
+
+    /// Fee in basis points of each cranked claim paid to the cranker, set by the grantor.
+    /// Only charged up to what the beneficiary approved in `crank_fee_approved_bps`.
+    pub crank_fee_bps: u16,
+
+    /// The highest crank fee in basis points the beneficiary agreed to pay, 0 until approved
+    pub crank_fee_approved_bps: u16,
+
+    /// The unit `vesting_period` is counted in, fixed seconds or calendar months, quarters and years
+    pub period_unit: PeriodUnit,
+
+    /// The shape of the schedule, periodic chunks, continuous linear vesting or irregular tranches.
+    /// Its size varies, fixed-size fields are kept in front of it.
+    pub curve: VestingCurve,
+}
+
+impl TokenVesting {
+    /// The layout version written by this program, older accounts have to be migrated first.
+    /// Bumped on any change of the layout, `migrate_vesting` has to convert every older version.
+    pub const LAYOUT_VERSION: u8 = 2;
+
+    /// Offset of the `version` byte, right behind the discriminator and the version 1 fields
+    pub const VERSION_OFFSET: usize = 8 + LegacyTokenVesting::SIZE;
+
+    pub const MAX_SIZE: usize = 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 1
+        + 32
+        + 32
+        + 32
+        + 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 1
+        + 2
+        + 2
+        + 1
+        + VestingCurve::MAX_SIZE;
+
+    /// Converts a version 1 account, the fields it predates get their defaults.
+    /// Version 1 grants have no grantor, so no grantor instruction applies to them.
+    pub fn from_legacy(legacy: LegacyTokenVesting) -> Self {
+        TokenVesting {
+            beneficiary: legacy.beneficiary,
+            mint: legacy.mint,
+            vesting_period: legacy.vesting_period,
+            duration: legacy.duration,
+            total_amount: legacy.total_amount,
+            start_time: legacy.start_time,
+            claimed_amount: legacy.claimed_amount,
+            passed_periods: legacy.passed_periods,
+            vault_bump: legacy.vault_bump,
+            bump: legacy.bump,
+            version: 1,
+            original_beneficiary: legacy.beneficiary,
+            claim_delegate: Pubkey::default(),
+            position_mint: Pubkey::default(),
+            grantor: Pubkey::default(),
+            template: Pubkey::default(),
+            cliff_duration: 0,
+            unlock_time: 0,
+            accelerated_amount: 0,
+            carried_amount: 0,
+            revocable: false,
+            revoked: false,
+            crank_fee_bps: 0,
+            crank_fee_approved_bps: 0,
+            period_unit: PeriodUnit::Seconds,
+            curve: VestingCurve::Periodic,
+        }
+    }
+
+    /// Whether the claim right is held through a position NFT
+    pub fn is_tokenized(&self) -> bool {
+        self.position_mint != Pubkey::default()
+    }
+
+    /// Whether `claimer` may claim this grant.
+    /// Tokenized grants are claimed by the holder of the position NFT in `position_ata`,
+    /// other grants by the beneficiary or their claim delegate.
+    pub fn can_claim(&self, claimer: &Pubkey, position_ata: Option<&TokenAccount>) -> bool {
+        if self.is_tokenized() {
+            return position_ata.is_some_and(|position| {
+                position.mint == self.position_mint
+                    && position.owner == *claimer
+                    && position.amount == 1
+            });
+        }
+        *claimer == self.beneficiary
+            || (self.claim_delegate != Pubkey::default() && *claimer == self.claim_delegate)
+    }
+
+    /// The owner of the token account claims are paid to, the claiming position holder for tokenized grants
+    pub fn claim_recipient(&self, claimer: &Pubkey) -> Pubkey {
+        if self.is_tokenized() {
+            *claimer
+        } else {
+            self.beneficiary
+        }
+    }
+
+    /// A grant is settled once everything has been claimed.
+    /// Revoking settles a grant right away, unless its vested tokens are still locked up.
+    pub fn is_settled(&self) -> bool {
+        self.claimed_amount >= self.total_amount
+    }
+
+    /// Whether vested tokens are held in the vault by the lockup at `now`
+    pub fn is_locked(&self, now: i64) -> bool {
+        now < self.unlock_time
+    }
+
+    /// Validates a claim at `now` and records it on the grant.
+    /// Claims everything vested but unclaimed, or only `requested_amount` of it when provided,
+    /// and returns the amount to transfer out of the vault.
+    pub fn record_claim(&mut self, now: i64, requested_amount: Option<u64>) -> Result<u64> {
+        // A revoked grant only holds the tokens settled to the beneficiary during a lockup
+        require!(
+            !self.revoked || self.claimed_amount < self.total_amount,
+            TokenVestingError::VestingRevoked
+        );
+
+        // Vested tokens can not leave the vault before the end of the lockup
+        require!(!self.is_locked(now), TokenVestingError::TokensLocked);
+
+        // Accelerated tokens are claimable right away, even before the start or the cliff
+        let accelerated = self.accelerated_amount > 0;
+
+        // Check for timelapse since vesting started, the start can be scheduled in the future
+        require!(
+            accelerated || now > self.start_time,
+            TokenVestingError::VestingNotStarted
+        );
+        let time_lapsed = now
+            .checked_sub(self.start_time)
+            .ok_or(TokenVestingError::Overflow)?;
+
+        // Nothing unlocks before the cliff, at the cliff every period accrued so far unlocks at once
+        require!(
+            accelerated || time_lapsed >= self.cliff_duration,
+            TokenVestingError::CliffNotReached
+        );
+
+        // Check for Vesting ended. It triggers only when both duration has ended and all tokens have been claimed by the benefiiary
+        require!(
+            !(time_lapsed >= self.duration && self.claimed_amount >= self.total_amount),
+            TokenVestingError::VestingEnded
+        );
+
+        // Calculate the periods and amount vested so far, leftover tokens are added to the last period
+        let (vested_periods, vested_amount) = self.vested_at(now)?;
+
+        // The amount beneficiary is eligible to claim at this moment
+        let vested_unclaimed = math::claimable(vested_amount, self.claimed_amount)?;
+        let claimable_amount = match requested_amount {
+            Some(amount) => {
+                require!(amount > 0, TokenVestingError::MustBeGreaterThenZero);
+                require!(
+                    amount <= vested_unclaimed,
+                    TokenVestingError::InvalidTokenAmount
+                );
+                amount
+            }
+            None => {
+                require!(
+                    vested_unclaimed > 0,
+                    TokenVestingError::VestingPeriodNotReached
+                );
+                vested_unclaimed
+            }
+        };
+        self.passed_periods = vested_periods;
+
+        // Add the claimable amount to claimed account for tracking
+        self.claimed_amount = self
+            .claimed_amount
+            .checked_add(claimable_amount)
+            .ok_or(TokenVestingError::Overflow)?;
+
+        // Check if beneficiary has overclaimed
+        require!(
+            self.claimed_amount <= self.total_amount,
+            TokenVestingError::OverClaimed
+        );
+
+        Ok(claimable_amount)
+    }
+
+    /// The timing parameters of the grant
+    pub fn schedule(&self) -> VestingSchedule {
+        VestingSchedule {
+            start_time: self.start_time,
+            cliff_duration: self.cliff_duration,
+            vesting_period: self.vesting_period,
+            period_unit: self.period_unit,
+            duration: self.duration,
+        }
+    }
+
+    /// Total number of vesting periods in the schedule
+    pub fn total_periods(&self) -> i64 {
+        math::total_periods(&self.schedule(), &self.curve)
+    }
+
+    /// The amount vested ahead of the schedule, accelerated by the grantor or carried over a split
+    pub fn up_front_amount(&self) -> Result<u64> {
+        Ok(self
+            .accelerated_amount
+            .checked_add(self.carried_amount)
+            .ok_or(TokenVestingError::Overflow)?)
+    }
+
+    /// Returns the number of periods vested at `now` and the amount vested by then,
+    /// including the amount accelerated by the grantor or carried over a split.
+    /// A revoked grant stays vested at what was settled when it was revoked.
+    pub fn vested_at(&self, now: i64) -> Result<(i64, u64)> {
+        if self.revoked {
+            return Ok((self.passed_periods, self.total_amount));
+        }
+        let (vested_periods, scheduled_amount) =
+            math::vested_at(&self.schedule(), &self.curve, self.total_amount, now)?;
+        let vested_amount =
+            math::with_acceleration(scheduled_amount, self.total_amount, self.up_front_amount()?)?;
+        Ok((vested_periods, vested_amount))
+    }
+
+    /// Returns what the grant has vested, claimed and can claim at `now`, and when more unlocks next.
+    /// During a lockup the vested but unclaimed tokens are reported as locked instead of claimable.
+    pub fn claimable_at(&self, now: i64) -> Result<ClaimableAmount> {
+        let (_, vested_amount) = self.vested_at(now)?;
+        let unclaimed_amount = math::claimable(vested_amount, self.claimed_amount)?;
+
+        // A revoked grant has been settled in full, nothing more vests
+        let next_vesting_time = if self.revoked {
+            None
+        } else {
+            math::next_unlock_time(&self.schedule(), &self.curve, now)?
+        };
+
+        if self.is_locked(now) {
+            // Everything vested by then becomes claimable at the end of the lockup
+            let next_unlock_time = if unclaimed_amount > 0 {
+                Some(self.unlock_time)
+            } else {
+                next_vesting_time.map(|time| time.max(self.unlock_time))
+            };
+            return Ok(ClaimableAmount {
+                vested_amount,
+                claimed_amount: self.claimed_amount,
+                claimable_amount: 0,
+                locked_amount: unclaimed_amount,
+                next_unlock_time,
+            });
+        }
+
+        Ok(ClaimableAmount {
+            vested_amount,
+            claimed_amount: self.claimed_amount,
+            claimable_amount: unclaimed_amount,
+            locked_amount: 0,
+            next_unlock_time: next_vesting_time,
+        })
+    }
+}
+
+/// The version 1 layout of `TokenVesting`, from before the layout version byte.
+/// Only read by `migrate_vesting`, the account shares the discriminator of `TokenVesting`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LegacyTokenVesting {
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub vesting_period: i64,
+    pub duration: i64,
+    pub total_amount: u64,
+    pub start_time: i64,
+    pub claimed_amount: u64,
+    pub passed_periods: i64,
+    pub vault_bump: u8,
+    pub bump: u8,
+}
+
+impl LegacyTokenVesting {
+    pub const SIZE: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+}
+
+/// The timing parameters of a vesting schedule.
+/// Also committed to by the Merkle leaves of a vesting pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VestingSchedule {
+    /// Starting timestamp of vesting
+    pub start_time: i64,
+
+    /// The cliff duration in seconds, counted from the start of vesting
+    pub cliff_duration: i64,
+
+    /// The vesting period in seconds, or in `period_unit` for calendar periods
+    pub vesting_period: i64,
+
+    /// The unit `vesting_period` is counted in
+    pub period_unit: PeriodUnit,
+
+    /// The duration of the vesting schedule in seconds
+    pub duration: i64,
+}
+
+/// Snapshot of a grant returned by the `get_claimable` view instruction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClaimableAmount {
+    /// The amount vested so far
+    pub vested_amount: u64,
+
+    /// The amount the beneficiary has claimed so far
+    pub claimed_amount: u64,
+
+    /// The vested amount that can be claimed right now
+    pub claimable_amount: u64,
+
+    /// The vested amount held back by the lockup, claimable once it ends
+    pub locked_amount: u64,
+
+    /// The next time more tokens vest, `None` once everything has vested
+    pub next_unlock_time: Option<i64>,
+}
+
+/// Represents a Merkle-distributor vesting pool.
+///
+/// A single vault funds many beneficiaries, each leaf of the Merkle root commits to
+/// a beneficiary, their total amount and their vesting schedule.
+#[account]
+pub struct VestingPool {
+    /// The grantor who funded the pool
+    pub grantor: Pubkey,
+
+    /// The SPL token mint for the token being vested
+    pub mint: Pubkey,
+
+    /// Root of the Merkle tree of `(beneficiary, total_amount, schedule)` leaves
+    pub merkle_root: [u8; 32],
+
+    /// The total amount of tokens deposited into the pool vault
+    pub total_amount: u64,
+
+    /// The amount claimed by all beneficiaries so far
+    pub claimed_amount: u64,
+
+    /// Index of the pool, allowing a grantor to run several pools
+    pub pool_index: u64,
+
+    /// The bump seed for the pool vault
+    pub vault_bump: u8,
+
+    /// The bump seed for the pool account
+    pub bump: u8,
+}
+
+impl VestingPool {
+    pub const MAX_SIZE: usize = 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1;
+}
+
+/// A reusable schedule a grantor authority creates grants from.
+/// Templates can not be changed once created, every grant made from one shares the same shape.
+#[account]
+pub struct VestingTemplate {
+    /// The grantor authority allowed to create grants from the template
+    pub authority: Pubkey,
+
+    /// Id of the template, allowing an authority to keep several templates
+    pub template_id: u64,
+
+    /// The vesting period, in seconds or in `period_unit` for calendar periods
+    pub vesting_period: i64,
+
+    /// The unit the vesting period, duration and cliff are counted in
+    pub period_unit: PeriodUnit,
+
+    /// The duration of the schedule, in seconds or in `period_unit` for calendar periods
+    pub duration: i64,
+
+    /// The cliff duration, in seconds or in `period_unit` for calendar periods
+    pub cliff_duration: i64,
+
+    /// The shape of the schedule, tranche schedules carry timestamps and can not be templated
+    pub curve: VestingCurve,
+
+    /// Whether grants created from the template can be revoked by the grantor
+    pub revocable: bool,
+
+    /// The bump seed for the template account
+    pub bump: u8,
+}
+
+impl VestingTemplate {
+    pub const MAX_SIZE: usize = 32 + 8 + 8 + 1 + 8 + 8 + VestingCurve::MAX_SIZE + 1 + 1;
+}
+
+/// Tracks the claims of a single beneficiary of a vesting pool
+#[account]
+pub struct ClaimStatus {
+    /// The pool the claims are made from
+    pub pool: Pubkey,
+
+    /// The beneficiary claiming from the pool
+    pub beneficiary: Pubkey,
+
+    /// The amount the beneficiary has claimed so far
+    pub claimed_amount: u64,
+
+    /// How many periods has passed when beneficiary claimed the vested amount
+    pub passed_periods: i64,
+
+    /// The bump seed for the claim status account
+    pub bump: u8,
+}
+
+impl ClaimStatus {
+    pub const MAX_SIZE: usize = 32 + 32 + 8 + 8 + 1;
 }
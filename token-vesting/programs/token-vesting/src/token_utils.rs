@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions},
+};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::error::TokenVestingError;
+
+/// Returns the Token-2022 transfer fee charged on sending `amount` of `mint` in the current epoch.
+/// Mints without the transfer-fee extension (including legacy SPL mints) charge no fee.
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => {
+            let epoch = Clock::get()?.epoch;
+            Ok(fee_config
+                .calculate_epoch_fee(epoch, amount)
+                .ok_or(TokenVestingError::Overflow)?)
+        }
+        Err(_) => Ok(0),
+    }
+}
+
+/// Returns the amount that has to be sent so that the receiver ends up with `net_amount`
+/// after the Token-2022 transfer fee of `mint` is withheld.
+pub fn gross_amount_for(mint: &InterfaceAccount<Mint>, net_amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => {
+            let epoch = Clock::get()?.epoch;
+            let fee = fee_config
+                .calculate_inverse_epoch_fee(epoch, net_amount)
+                .ok_or(TokenVestingError::Overflow)?;
+            Ok(net_amount
+                .checked_add(fee)
+                .ok_or(TokenVestingError::Overflow)?)
+        }
+        Err(_) => Ok(net_amount),
+    }
+}
+
+/// Funds `vault` with `net_amount` of `mint` from `from`, charging any Token-2022 transfer fee
+/// on top so the vault receives the full amount. Returns the amount the vault received.
+pub fn fund_vault<'info>(
+    token_program: &AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    net_amount: u64,
+) -> Result<u64> {
+    let vault_before = vault.amount;
+    let gross_amount = gross_amount_for(mint, net_amount)?;
+    transfer_checked(
+        token_program,
+        from,
+        mint,
+        vault.to_account_info(),
+        authority,
+        remaining_accounts,
+        gross_amount,
+        &[],
+    )?;
+
+    // The vault accounting is based on what was actually received
+    vault.reload()?;
+    let received_amount = vault
+        .amount
+        .checked_sub(vault_before)
+        .ok_or(TokenVestingError::Overflow)?;
+    require!(
+        received_amount >= net_amount,
+        TokenVestingError::NotEnoughToken
+    );
+    Ok(received_amount)
+}
+
+/// Transfers tokens with `transfer_checked` through either token program.
+/// For mints with the transfer-hook extension the extra accounts required by the hook
+/// are looked up in `remaining_accounts`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'info>(
+    token_program: &AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        token_program.key,
+        from,
+        mint.to_account_info(),
+        to,
+        authority,
+        remaining_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds,
+    )?;
+    Ok(())
+}
@@ -1,14 +1,25 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { TokenVesting } from "../target/types/token_vesting";
+import { MockStaking } from "../target/types/mock_staking";
+import { MockTransferHook } from "../target/types/mock_transfer_hook";
 import {
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
   createAssociatedTokenAccount,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeTransferHookInstruction,
   createMint,
   getAccount,
   getAssociatedTokenAddress,
+  getMint,
+  getMintLen,
   mintTo,
+  transfer,
 } from "@solana/spl-token";
 import { assert } from "chai";
+import { createHash } from "crypto";
 
 describe("token-vesting", () => {
   // Configure the client to use the local cluster.
@@ -16,6 +27,9 @@ describe("token-vesting", () => {
 
   const provider = anchor.getProvider();
   const program = anchor.workspace.tokenVesting as Program<TokenVesting>;
+  const stakingProgram = anchor.workspace.mockStaking as Program<MockStaking>;
+  const hookProgram = anchor.workspace
+    .mockTransferHook as Program<MockTransferHook>;
 
   const admin = anchor.web3.Keypair.generate();
   const beneficiary = anchor.web3.Keypair.generate();
@@ -65,14 +79,6 @@ describe("token-vesting", () => {
       program.programId
     );
 
-    const [vaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
-      [
-        Buffer.from("vault"),
-        mint.toBuffer(),
-        indexBN.toArrayLike(Buffer, "le", 8),
-      ],
-      program.programId
-    );
     const [vestingAccountPda, vestingAccountBump] =
       anchor.web3.PublicKey.findProgramAddressSync(
         [
@@ -82,6 +88,10 @@ describe("token-vesting", () => {
         ],
         program.programId
       );
+    const [vaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
     vestingsParams[index] = {
       index: indexBN,
       mint,
@@ -97,6 +107,11 @@ describe("token-vesting", () => {
 
   const connection = provider.connection;
 
+  async function currentTimestamp(): Promise<number> {
+    const slot = await connection.getSlot();
+    return await connection.getBlockTime(slot);
+  }
+
   before(async () => {
     const airdropSig = await provider.connection.requestAirdrop(
       admin.publicKey,
@@ -105,6 +120,11 @@ describe("token-vesting", () => {
     await provider.connection.confirmTransaction(airdropSig);
     await setupVesting(2, 1_000_000_000);
     await setupVesting(3, 1_000_000_000);
+    await setupVesting(4, 1_000_000_000);
+    await setupVesting(5, 1_000_000_000);
+    await setupVesting(6, 1_000_000_000);
+    await setupVesting(7, 1_000_000_000);
+    await setupVesting(8, 1_000_000_000);
   });
 
   it("Fail on initalization for total amount should be greater than zero", async () => {
@@ -122,7 +142,13 @@ describe("token-vesting", () => {
           vestingPeriod,
           duration,
           totalAmount,
-          index
+          index,
+          new anchor.BN(0),
+          new anchor.BN(await currentTimestamp()),
+          false,
+          { periodic: {} },
+          new anchor.BN(0),
+          { seconds: {} }
         )
         .accounts({
           user: admin.publicKey,
@@ -160,7 +186,13 @@ describe("token-vesting", () => {
           vestingPeriod,
           duration,
           totalAmount,
-          index
+          index,
+          new anchor.BN(0),
+          new anchor.BN(await currentTimestamp()),
+          false,
+          { periodic: {} },
+          new anchor.BN(0),
+          { seconds: {} }
         )
         .accounts({
           user: admin.publicKey,
@@ -201,7 +233,13 @@ describe("token-vesting", () => {
           vestingPeriod,
           duration,
           totalAmount,
-          index
+          index,
+          new anchor.BN(0),
+          new anchor.BN(await currentTimestamp()),
+          false,
+          { periodic: {} },
+          new anchor.BN(0),
+          { seconds: {} }
         )
         .accounts({
           user: admin.publicKey,
@@ -239,7 +277,13 @@ describe("token-vesting", () => {
           vestingPeriod,
           duration,
           totalAmount,
-          index
+          index,
+          new anchor.BN(0),
+          new anchor.BN(await currentTimestamp()),
+          false,
+          { periodic: {} },
+          new anchor.BN(0),
+          { seconds: {} }
         )
         .accounts({
           user: admin.publicKey,
@@ -277,7 +321,13 @@ describe("token-vesting", () => {
         vestingPeriod,
         duration,
         totalAmount,
-        index
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
       )
       .accounts({
         user: admin.publicKey,
@@ -373,7 +423,13 @@ describe("token-vesting", () => {
         vestingPeriod,
         duration,
         totalAmount,
-        index
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
       )
       .accounts({
         user: admin.publicKey,
@@ -450,4 +506,2886 @@ describe("token-vesting", () => {
 
     console.log(beneficiaryAccount.amount.toString());
   });
+
+  it("Fails to claim before the cliff is reached", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = vestingsParams[4];
+    const vestingPeriod = new anchor.BN(2); // 2 seconds
+    const duration = new anchor.BN(10); // 10 seconds
+    const cliffDuration = new anchor.BN(6); // 6 seconds
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        vestingPeriod,
+        duration,
+        totalAmount,
+        index,
+        cliffDuration,
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    try {
+      await new Promise((res) => setTimeout(res, 3000));
+      await program.methods
+        .claimVestedToken(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+      assert.fail("expected CliffNotReached");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "CliffNotReached");
+    }
+  });
+
+  it("Claims every period accrued so far once the cliff is reached", async () => {
+    const { mint, vaultAta, vestingAccountPda, index, beneficiaryAta } =
+      vestingsParams[4];
+    const vestingBefore = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const cliffTime = vestingBefore.startTime
+      .add(vestingBefore.cliffDuration)
+      .toNumber();
+    while ((await currentTimestamp()) < cliffTime) {
+      await new Promise((res) => setTimeout(res, 400));
+    }
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+
+    // The 3 periods accrued during the 6 second cliff unlock together
+    assert.ok(vestingAccount.claimedAmount.gte(new anchor.BN(600_000_000)));
+    assert.equal(
+      (beneficiaryAfter.amount - beneficiaryBefore.amount).toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+  });
+
+  it("Fail on initalization for start time too far in the past", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      vestingsParams[5];
+    const vestingPeriod = new anchor.BN(2); // 2 seconds
+    const duration = new anchor.BN(10); // 10 seconds
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+    const startTime = new anchor.BN(
+      (await currentTimestamp()) - 5 * 365 * 24 * 60 * 60
+    ); // 5 years ago
+
+    try {
+      await program.methods
+        .initializeVesting(
+          mint,
+          beneficiary.publicKey,
+          vestingPeriod,
+          duration,
+          totalAmount,
+          index,
+          new anchor.BN(0),
+          startTime,
+          false,
+          { periodic: {} },
+          new anchor.BN(0),
+          { seconds: {} }
+        )
+        .accounts({
+          user: admin.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta: vaultAta,
+          mint,
+          adminAta: userAta,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("expected InvalidStartTime");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "InvalidStartTime");
+    }
+  });
+
+  it("Fails to claim when vesting starts in the future", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = vestingsParams[5];
+    const vestingPeriod = new anchor.BN(2); // 2 seconds
+    const duration = new anchor.BN(10); // 10 seconds
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+    const startTime = new anchor.BN((await currentTimestamp()) + 24 * 60 * 60); // 1 day ahead
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        vestingPeriod,
+        duration,
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        startTime,
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    assert.equal(vestingAccount.startTime.toString(), startTime.toString());
+
+    try {
+      await new Promise((res) => setTimeout(res, 3000));
+      await program.methods
+        .claimVestedToken(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+      assert.fail("expected VestingNotStarted");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "VestingNotStarted");
+    }
+  });
+
+  it("Revokes vesting and returns unvested tokens to the grantor", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = vestingsParams[6];
+    const vestingPeriod = new anchor.BN(2); // 2 seconds
+    const duration = new anchor.BN(100); // 100 seconds
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        vestingPeriod,
+        duration,
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        true,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await program.methods
+      .revokeVesting(index)
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta,
+        grantorAta: userAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+    const userAfter = await getAccount(connection, userAta);
+    const vaultAfter = await getAccount(connection, vaultAta);
+
+    assert.isTrue(vestingAccount.revoked);
+    assert.equal(vaultAfter.amount.toString(), "0");
+    assert.equal(
+      (beneficiaryAfter.amount - beneficiaryBefore.amount).toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+    assert.equal(
+      userAfter.amount.toString(),
+      new anchor.BN(2_000_000_000)
+        .sub(vestingAccount.claimedAmount)
+        .toString()
+    );
+
+    try {
+      await program.methods
+        .claimVestedToken(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+      assert.fail("expected VestingRevoked");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "VestingRevoked");
+    }
+  });
+
+  it("Claims continuously vested tokens with a linear curve", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = vestingsParams[7];
+    const vestingPeriod = new anchor.BN(1); // 1 second
+    const duration = new anchor.BN(100); // 100 seconds
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        vestingPeriod,
+        duration,
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { linear: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 3000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+    const claimed = beneficiaryAfter.amount - beneficiaryBefore.amount;
+
+    assert.isTrue(claimed > BigInt(0));
+    assert.isTrue(claimed < BigInt(totalAmount.toString()));
+    assert.equal(claimed.toString(), vestingAccount.claimedAmount.toString());
+  });
+
+  it("Fail on initalization for tranches not summing to total amount", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      vestingsParams[8];
+    const now = await currentTimestamp();
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+    const tranches = [
+      {
+        unlockTimestamp: new anchor.BN(now),
+        amount: new anchor.BN(100_000_000),
+      },
+      {
+        unlockTimestamp: new anchor.BN(now + 60),
+        amount: new anchor.BN(100_000_000),
+      },
+    ];
+
+    try {
+      await program.methods
+        .initializeVesting(
+          mint,
+          beneficiary.publicKey,
+          new anchor.BN(0),
+          new anchor.BN(0),
+          totalAmount,
+          index,
+          new anchor.BN(0),
+          new anchor.BN(now),
+          false,
+          { tranched: { tranches } },
+          new anchor.BN(0),
+          { seconds: {} }
+        )
+        .accounts({
+          user: admin.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta: vaultAta,
+          mint,
+          adminAta: userAta,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("expected TrancheAmountMismatch");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "TrancheAmountMismatch");
+    }
+  });
+
+  it("Claims every tranche whose unlock time has passed", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = vestingsParams[8];
+    const now = await currentTimestamp();
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+    const tranches = [
+      {
+        unlockTimestamp: new anchor.BN(now),
+        amount: new anchor.BN(100_000_000),
+      }, // 10% at TGE
+      { unlockTimestamp: new anchor.BN(now + 2), amount: new anchor.BN(0) },
+      {
+        unlockTimestamp: new anchor.BN(now + 4),
+        amount: new anchor.BN(400_000_000),
+      },
+      {
+        unlockTimestamp: new anchor.BN(now + 600),
+        amount: new anchor.BN(500_000_000),
+      },
+    ];
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(0),
+        new anchor.BN(0),
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(now),
+        false,
+        { tranched: { tranches } },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 6000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+
+    assert.equal(
+      (beneficiaryAfter.amount - beneficiaryBefore.amount).toString(),
+      "500000000"
+    );
+    assert.equal(vestingAccount.passedPeriods.toString(), "3");
+  });
+
+  it("Initialize token vesting with a Token-2022 transfer fee mint", async () => {
+    const index = new anchor.BN(9);
+    const payer = provider.wallet.payer;
+    const mintKeypair = anchor.web3.Keypair.generate();
+    const mint = mintKeypair.publicKey;
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    const lamports = await connection.getMinimumBalanceForRentExemption(
+      mintLen
+    );
+
+    // 1% transfer fee
+    const createMintTx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(
+        mint,
+        payer.publicKey,
+        payer.publicKey,
+        100,
+        BigInt(1_000_000_000),
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        mint,
+        9,
+        payer.publicKey,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await anchor.web3.sendAndConfirmTransaction(connection, createMintTx, [
+      payer,
+      mintKeypair,
+    ]);
+
+    const userAta = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      mint,
+      admin.publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      payer,
+      mint,
+      userAta,
+      payer,
+      2_000_000_000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const [vestingAccountPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        beneficiary.publicKey.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [vaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const userAfter = await getAccount(
+      connection,
+      userAta,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const vaultAfter = await getAccount(
+      connection,
+      vaultAta,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    // The vault holds the full amount, the grantor paid the fee on top
+    assert.equal(vaultAfter.amount.toString(), totalAmount.toString());
+    assert.isTrue(userAfter.amount < BigInt(1_000_000_000));
+  });
+
+  it("Vests and claims a Token-2022 mint with a transfer hook", async () => {
+    const index = new anchor.BN(25);
+    const payer = provider.wallet.payer;
+    const mintKeypair = anchor.web3.Keypair.generate();
+    const mint = mintKeypair.publicKey;
+    const mintLen = getMintLen([ExtensionType.TransferHook]);
+    const lamports = await connection.getMinimumBalanceForRentExemption(
+      mintLen
+    );
+
+    const createMintTx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferHookInstruction(
+        mint,
+        payer.publicKey,
+        hookProgram.programId,
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        mint,
+        9,
+        payer.publicKey,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await anchor.web3.sendAndConfirmTransaction(connection, createMintTx, [
+      payer,
+      mintKeypair,
+    ]);
+
+    const [extraAccountMetaList] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("extra-account-metas"), mint.toBuffer()],
+      hookProgram.programId
+    );
+    const [counter] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("counter"), mint.toBuffer()],
+      hookProgram.programId
+    );
+    await hookProgram.methods
+      .initializeExtraAccountMetaList()
+      .accounts({
+        payer: admin.publicKey,
+        extraAccountMetaList,
+        counter,
+        mint,
+      })
+      .signers([admin])
+      .rpc();
+
+    const userAta = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      mint,
+      admin.publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const beneficiaryAta = await createAssociatedTokenAccount(
+      connection,
+      payer,
+      mint,
+      beneficiary.publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      payer,
+      mint,
+      userAta,
+      payer,
+      2_000_000_000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const [vestingAccountPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        beneficiary.publicKey.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [vaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+
+    // The token program resolves the hook accounts from the remaining accounts
+    const hookAccounts = [
+      { pubkey: extraAccountMetaList, isSigner: false, isWritable: false },
+      { pubkey: hookProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: counter, isSigner: false, isWritable: true },
+    ];
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookAccounts)
+      .signers([admin])
+      .rpc();
+
+    const vaultAfterInit = await getAccount(
+      connection,
+      vaultAta,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(vaultAfterInit.amount.toString(), totalAmount.toString());
+    assert.equal(
+      (await hookProgram.account.counter.fetch(counter)).transfers.toNumber(),
+      1
+    );
+
+    await new Promise((res) => setTimeout(res, 3000));
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta,
+        mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts(hookAccounts)
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(
+      connection,
+      beneficiaryAta,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    assert.ok(vestingAccount.claimedAmount.gtn(0));
+    assert.equal(
+      beneficiaryAfter.amount.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+    assert.equal(
+      (await hookProgram.account.counter.fetch(counter)).transfers.toNumber(),
+      2
+    );
+  });
+
+  it("Claims part of the vested amount", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(10, 1_000_000_000);
+    const vestingPeriod = new anchor.BN(2); // 2 seconds
+    const duration = new anchor.BN(10); // 10 seconds
+    const totalAmount = new anchor.BN(1_000_000_000); // 1 tokens (9 decimals)
+    const requestedAmount = new anchor.BN(1_000); // less than one period
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        vestingPeriod,
+        duration,
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await program.methods
+      .claimVestedAmount(index, requestedAmount)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+
+    assert.equal(
+      (beneficiaryAfter.amount - beneficiaryBefore.amount).toString(),
+      requestedAmount.toString()
+    );
+    assert.equal(
+      vestingAccount.claimedAmount.toString(),
+      requestedAmount.toString()
+    );
+
+    try {
+      await program.methods
+        .claimVestedAmount(index, totalAmount)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+      assert.fail("expected InvalidTokenAmount");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "InvalidTokenAmount");
+    }
+  });
+
+  it("Closes a revoked vesting and returns the rent to the grantor", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      vestingsParams[6];
+
+    // A live grant still owes its beneficiary the tokens in its vault
+    const live = vestingsParams[10];
+    try {
+      await program.methods
+        .closeVesting(live.index)
+        .accounts({
+          grantor: admin.publicKey,
+          vestingAccount: live.vestingAccountPda,
+          vaultAta: live.vaultAta,
+          grantorAta: live.userAta,
+          mint: live.mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("a live grant was closed");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "VestingNotSettled");
+    }
+    const liveVault = await getAccount(connection, live.vaultAta);
+    assert.ok(liveVault.amount > BigInt(0));
+
+    const grantorBefore = await connection.getBalance(admin.publicKey);
+
+    await program.methods
+      .closeVesting(index)
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        grantorAta: userAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const grantorAfter = await connection.getBalance(admin.publicKey);
+    const vestingInfo = await connection.getAccountInfo(vestingAccountPda);
+    const vaultInfo = await connection.getAccountInfo(vaultAta);
+
+    assert.isNull(vestingInfo);
+    assert.isNull(vaultInfo);
+    assert.isTrue(grantorAfter > grantorBefore);
+  });
+
+  it("Claims from a Merkle vesting pool with a proof", async () => {
+    const { mint, userAta, beneficiaryAta } = await setupVesting(
+      11,
+      1_000_000_000
+    );
+    const poolIndex = new anchor.BN(0);
+    const otherBeneficiary = anchor.web3.Keypair.generate();
+    const now = await currentTimestamp();
+    const schedule = {
+      startTime: new anchor.BN(now),
+      cliffDuration: new anchor.BN(0),
+      vestingPeriod: new anchor.BN(2),
+      periodUnit: { seconds: {} },
+      duration: new anchor.BN(10),
+    };
+
+    function leafHash(wallet: anchor.web3.PublicKey, amount: anchor.BN) {
+      return createHash("sha256")
+        .update(
+          Buffer.concat([
+            Buffer.from([0]),
+            wallet.toBuffer(),
+            amount.toArrayLike(Buffer, "le", 8),
+            schedule.startTime.toTwos(64).toArrayLike(Buffer, "le", 8),
+            schedule.cliffDuration.toTwos(64).toArrayLike(Buffer, "le", 8),
+            schedule.vestingPeriod.toTwos(64).toArrayLike(Buffer, "le", 8),
+            Buffer.from([0]), // PeriodUnit::Seconds
+            schedule.duration.toTwos(64).toArrayLike(Buffer, "le", 8),
+          ])
+        )
+        .digest();
+    }
+
+    const amount = new anchor.BN(600_000_000);
+    const leaf = leafHash(beneficiary.publicKey, amount);
+    const sibling = leafHash(
+      otherBeneficiary.publicKey,
+      new anchor.BN(400_000_000)
+    );
+    const [left, right] =
+      Buffer.compare(leaf, sibling) <= 0 ? [leaf, sibling] : [sibling, leaf];
+    const root = createHash("sha256")
+      .update(Buffer.concat([Buffer.from([1]), left, right]))
+      .digest();
+
+    const [poolPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("pool"),
+        admin.publicKey.toBuffer(),
+        poolIndex.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [poolVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_vault"), poolPda.toBuffer()],
+      program.programId
+    );
+    const [claimStatus] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("claim_status"),
+        poolPda.toBuffer(),
+        beneficiary.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .initializePool(poolIndex, Array.from(root), new anchor.BN(1_000_000_000))
+      .accounts({
+        grantor: admin.publicKey,
+        pool: poolPda,
+        poolVault,
+        mint,
+        grantorAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+    const events: { name: string; event: any }[] = [];
+    const listeners = ["grantCreated", "tokensClaimed"].map((name) =>
+      program.addEventListener(name as any, (event) =>
+        events.push({ name, event })
+      )
+    );
+
+    await program.methods
+      .claimFromPool(poolIndex, amount, schedule, [Array.from(sibling)])
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        pool: poolPda,
+        claimStatus,
+        poolVault,
+        beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const status = await program.account.claimStatus.fetch(claimStatus);
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+
+    assert.isTrue(status.passedPeriods.toNumber() >= 2);
+    assert.equal(
+      (beneficiaryAfter.amount - beneficiaryBefore.amount).toString(),
+      status.claimedAmount.toString()
+    );
+
+    await new Promise((res) => setTimeout(res, 1000));
+    for (const listener of listeners) {
+      await program.removeEventListener(listener);
+    }
+
+    // Pool grants are announced on their first claim, keyed by the claim status
+    const created = events.find((e) => e.name === "grantCreated");
+    const claimed = events.find((e) => e.name === "tokensClaimed");
+    assert.equal(
+      created.event.vestingAccount.toBase58(),
+      claimStatus.toBase58()
+    );
+    assert.equal(created.event.totalAmount.toString(), amount.toString());
+    assert.equal(
+      claimed.event.vestingAccount.toBase58(),
+      claimStatus.toBase58()
+    );
+    assert.equal(
+      claimed.event.receivedAmount.toString(),
+      status.claimedAmount.toString()
+    );
+  });
+
+  it("Tops up an existing vesting schedule", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(26, 1_000_000_000);
+    const topUpAmount = new anchor.BN(500_000_000);
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    // Top up part-way through the schedule, after the first period has passed
+    await new Promise((res) => setTimeout(res, 3000));
+    const vestingBefore = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const vaultBefore = await getAccount(connection, vaultAta);
+    const events: any[] = [];
+    const listener = program.addEventListener("grantToppedUp", (event) =>
+      events.push(event)
+    );
+
+    await program.methods
+      .addToVesting(index, topUpAmount)
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        grantorAta: userAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vestingAfter = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const vaultAfter = await getAccount(connection, vaultAta);
+
+    assert.equal(
+      vestingAfter.totalAmount.toString(),
+      vestingBefore.totalAmount.add(topUpAmount).toString()
+    );
+    assert.equal(
+      (vaultAfter.amount - vaultBefore.amount).toString(),
+      topUpAmount.toString()
+    );
+    assert.equal(
+      vestingAfter.claimedAmount.toString(),
+      vestingBefore.claimedAmount.toString()
+    );
+
+    await new Promise((res) => setTimeout(res, 1000));
+    await program.removeEventListener(listener);
+    assert.equal(events.length, 1);
+    assert.equal(events[0].amount.toString(), topUpAmount.toString());
+    assert.equal(
+      events[0].totalAmount.toString(),
+      vestingAfter.totalAmount.toString()
+    );
+
+    // Periods that already passed release their share of the new total, 1500000000 over 5 periods
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingClaimed = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+    const passedPeriods = vestingClaimed.passedPeriods.toNumber();
+    assert.isTrue(passedPeriods > 0 && passedPeriods < 5);
+    assert.equal(
+      vestingClaimed.claimedAmount.toString(),
+      new anchor.BN(300_000_000).muln(passedPeriods).toString()
+    );
+    assert.equal(
+      beneficiaryAfter.amount.toString(),
+      vestingClaimed.claimedAmount.toString()
+    );
+  });
+
+  it("Cranks a claim to the beneficiary and pays the cranker fee", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(12, 1_000_000_000);
+    const cranker = anchor.web3.Keypair.generate();
+    const crankerAta = await createAssociatedTokenAccount(
+      connection,
+      provider.wallet.payer,
+      mint,
+      cranker.publicKey
+    );
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .setCrankFee(index, 100) // 1%
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([admin])
+      .rpc();
+
+    const crankClaim = () =>
+      program.methods
+        .crankClaim(index)
+        .accounts({
+          cranker: cranker.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta,
+          crankerAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([cranker])
+        .rpc();
+
+    // No fee is charged until the beneficiary approves it
+    await new Promise((res) => setTimeout(res, 3000));
+    await crankClaim();
+
+    const vestingBefore = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const crankerBefore = await getAccount(connection, crankerAta);
+    assert.equal(crankerBefore.amount.toString(), "0");
+
+    await program.methods
+      .approveCrankFee(index, 100)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 4000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await crankClaim();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+    const crankerAfter = await getAccount(connection, crankerAta);
+    const received = beneficiaryAfter.amount - beneficiaryBefore.amount;
+    const cranked = vestingAccount.claimedAmount.sub(
+      vestingBefore.claimedAmount
+    );
+
+    assert.equal(
+      (received + crankerAfter.amount).toString(),
+      cranked.toString()
+    );
+    assert.equal(
+      crankerAfter.amount.toString(),
+      cranked.muln(100).divn(10_000).toString()
+    );
+  });
+
+  it("Claims through a delegate and reassigns the beneficiary", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(13, 1_000_000_000);
+    const delegate = anchor.web3.Keypair.generate();
+    const newBeneficiary = anchor.web3.Keypair.generate();
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .setClaimDelegate(index, delegate.publicKey)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+    const beneficiaryBefore = await getAccount(connection, beneficiaryAta);
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: delegate.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([delegate])
+      .rpc();
+
+    let vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await getAccount(connection, beneficiaryAta);
+
+    assert.equal(
+      (beneficiaryAfter.amount - beneficiaryBefore.amount).toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+
+    await program.methods
+      .transferBeneficiary(index, newBeneficiary.publicKey)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([beneficiary, admin])
+      .rpc();
+
+    vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+
+    assert.equal(
+      vestingAccount.beneficiary.toBase58(),
+      newBeneficiary.publicKey.toBase58()
+    );
+    assert.equal(
+      vestingAccount.originalBeneficiary.toBase58(),
+      beneficiary.publicKey.toBase58()
+    );
+    assert.equal(
+      vestingAccount.claimDelegate.toBase58(),
+      anchor.web3.PublicKey.default.toBase58()
+    );
+  });
+
+  it("Emits events for grant creation and claims", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(14, 1_000_000_000);
+    const events: { name: string; event: any }[] = [];
+    const names = ["grantCreated", "tokensClaimed", "claimDelegateSet"];
+    const listeners = names.map((name) =>
+      program.addEventListener(name as any, (event) =>
+        events.push({ name, event })
+      )
+    );
+    const delegate = anchor.web3.Keypair.generate();
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    await program.methods
+      .setClaimDelegate(index, delegate.publicKey)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 1000));
+    for (const listener of listeners) {
+      await program.removeEventListener(listener);
+    }
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const created = events.find((e) => e.name === "grantCreated");
+    const claimed = events.find((e) => e.name === "tokensClaimed");
+
+    assert.equal(
+      created.event.vestingAccount.toBase58(),
+      vestingAccountPda.toBase58()
+    );
+    assert.equal(created.event.totalAmount.toString(), "1000000000");
+    assert.ok(created.event.template.equals(anchor.web3.PublicKey.default));
+    assert.equal(
+      claimed.event.amount.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+    // No transfer fee on a legacy SPL mint, the beneficiary receives the full amount
+    assert.equal(
+      claimed.event.receivedAmount.toString(),
+      claimed.event.amount.toString()
+    );
+    assert.equal(
+      claimed.event.passedPeriods.toString(),
+      vestingAccount.passedPeriods.toString()
+    );
+
+    const delegateSet = events.find((e) => e.name === "claimDelegateSet");
+    assert.equal(
+      delegateSet.event.claimDelegate.toBase58(),
+      delegate.publicKey.toBase58()
+    );
+    assert.equal(delegateSet.event.index.toString(), index.toString());
+  });
+
+  it("Views the claimable amount through return data", async () => {
+    const { vestingAccountPda, index } = vestingsParams[14];
+
+    const claimable = await program.methods
+      .getClaimable(index)
+      .accounts({
+        vestingAccount: vestingAccountPda,
+      })
+      .view();
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+
+    assert.equal(
+      claimable.claimedAmount.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+    assert.equal(
+      claimable.claimableAmount.toString(),
+      claimable.vestedAmount.sub(claimable.claimedAmount).toString()
+    );
+    assert.ok(claimable.vestedAmount.gte(vestingAccount.claimedAmount));
+  });
+
+  it("Initializes grants of the same mint and index for two beneficiaries", async () => {
+    const { mint, userAta, index, vaultAta } = vestingsParams[14];
+    const otherBeneficiary = anchor.web3.Keypair.generate();
+    const [otherVestingPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        otherBeneficiary.publicKey.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [otherVaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), otherVestingPda.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        otherBeneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(500_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: otherVestingPda,
+        vaultAta: otherVaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      otherVestingPda
+    );
+    const otherVault = await getAccount(connection, otherVaultAta);
+
+    assert.notEqual(otherVaultAta.toBase58(), vaultAta.toBase58());
+    assert.equal(vestingAccount.version, 2);
+    assert.equal(otherVault.amount.toString(), "500000000");
+  });
+
+  it("Vests native SOL through a system-owned vault", async () => {
+    const index = new anchor.BN(15);
+    const [vestingAccountPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        beneficiary.publicKey.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [solVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("sol_vault"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
+    const totalAmount = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL);
+
+    await program.methods
+      .initializeSolVesting(
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        totalAmount,
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        { periodic: {} },
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        solVault,
+      })
+      .signers([admin])
+      .rpc();
+
+    const rentReserve = await connection.getMinimumBalanceForRentExemption(0);
+    assert.equal(
+      await connection.getBalance(solVault),
+      anchor.web3.LAMPORTS_PER_SOL + rentReserve
+    );
+
+    await new Promise((res) => setTimeout(res, 5000));
+    const beneficiaryBefore = await connection.getBalance(
+      beneficiary.publicKey
+    );
+
+    await program.methods
+      .claimVestedSol(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        solVault,
+        beneficiaryWallet: beneficiary.publicKey,
+        grantor: admin.publicKey,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const beneficiaryAfter = await connection.getBalance(beneficiary.publicKey);
+
+    assert.ok(vestingAccount.claimedAmount.gtn(0));
+    assert.equal(
+      beneficiaryAfter - beneficiaryBefore,
+      vestingAccount.claimedAmount.toNumber()
+    );
+    assert.equal(
+      await connection.getBalance(solVault),
+      totalAmount.sub(vestingAccount.claimedAmount).toNumber() + rentReserve
+    );
+
+    const closeSolVesting = () =>
+      program.methods
+        .closeSolVesting(index)
+        .accounts({
+          grantor: admin.publicKey,
+          vestingAccount: vestingAccountPda,
+          solVault,
+        })
+        .signers([admin])
+        .rpc();
+
+    try {
+      await closeSolVesting();
+      assert.fail("expected VestingNotSettled");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "VestingNotSettled");
+    }
+
+    // The final claim settles the grant and returns the rent reserve of the vault
+    await new Promise((res) => setTimeout(res, 6000));
+    await program.methods
+      .claimVestedSol(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        solVault,
+        beneficiaryWallet: beneficiary.publicKey,
+        grantor: admin.publicKey,
+      })
+      .signers([beneficiary])
+      .rpc();
+    assert.equal(await connection.getBalance(solVault), 0);
+
+    await closeSolVesting();
+
+    assert.isNull(await connection.getAccountInfo(vestingAccountPda));
+  });
+
+  it("Fail on initalization for graded weights not summing to 10000", async () => {
+    const { mint, userAta, index } = vestingsParams[14];
+    const otherBeneficiary = anchor.web3.Keypair.generate();
+    const [otherVestingPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        otherBeneficiary.publicKey.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [otherVaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), otherVestingPda.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .initializeVesting(
+          mint,
+          otherBeneficiary.publicKey,
+          new anchor.BN(2),
+          new anchor.BN(8),
+          new anchor.BN(100_000_000),
+          index,
+          new anchor.BN(0),
+          new anchor.BN(await currentTimestamp()),
+          false,
+          { graded: { weightsBps: [1000, 2000, 3000, 3000] } },
+          new anchor.BN(0),
+          { seconds: {} }
+        )
+        .accounts({
+          user: admin.publicKey,
+          vestingAccount: otherVestingPda,
+          vaultAta: otherVaultAta,
+          mint,
+          adminAta: userAta,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("graded weights must sum to 10000");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "InvalidWeights");
+    }
+  });
+
+  it("Claims back-loaded periods of a graded schedule", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(16, 1_000_000_000);
+    const weightsBps = [1000, 2000, 3000, 4000];
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(8),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { graded: { weightsBps } },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const passedPeriods = vestingAccount.passedPeriods.toNumber();
+    const expected = weightsBps
+      .slice(0, passedPeriods)
+      .reduce((sum, weight) => sum + (1_000_000_000 * weight) / 10_000, 0);
+
+    assert.equal(vestingAccount.claimedAmount.toNumber(), expected);
+  });
+
+  it("Claims accelerated tokens right away, before the cliff", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(17, 1_000_000_000);
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(10),
+        new anchor.BN(1000),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(500), // cliff far ahead
+        new anchor.BN(await currentTimestamp()),
+        true,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .accelerateVesting(index, { percentage: { bps: 5000 } })
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: beneficiaryAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+
+    assert.equal(vestingAccount.acceleratedAmount.toString(), "500000000");
+    assert.equal(vestingAccount.claimedAmount.toString(), "500000000");
+
+    // A second claim must not pay the accelerated amount again
+    try {
+      await program.methods
+        .claimVestedToken(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+      assert.fail("accelerated tokens were paid twice");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(
+        anchorError.error.errorCode.code,
+        "VestingPeriodNotReached"
+      );
+    }
+  });
+
+  it("Accelerates whole periods and vests the rest on schedule", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(27, 1_000_000_000);
+    const periodAmount = new anchor.BN(200_000_000); // 5 periods
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN((await currentTimestamp()) + 2), // no period vests before the acceleration
+        true,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .accelerateVesting(index, { periods: { count: 2 } })
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+      })
+      .signers([admin])
+      .rpc();
+
+    // The vested amount grows by exactly two periods
+    const claimable = await program.methods
+      .getClaimable(index)
+      .accounts({
+        vestingAccount: vestingAccountPda,
+      })
+      .view();
+    assert.equal(
+      claimable.vestedAmount.toString(),
+      periodAmount.muln(2).toString()
+    );
+
+    const claim = () =>
+      program.methods
+        .claimVestedToken(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+    await claim();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    assert.equal(
+      vestingAccount.acceleratedAmount.toString(),
+      periodAmount.muln(2).toString()
+    );
+    assert.equal(
+      vestingAccount.claimedAmount.toString(),
+      periodAmount.muln(2).toString()
+    );
+
+    // Later periods release the rest of the grant, spread over all 5 periods
+    await new Promise((res) => setTimeout(res, 5000));
+    await claim();
+
+    const vestingAfter = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const passedPeriods = vestingAfter.passedPeriods.toNumber();
+    const restPerPeriod = new anchor.BN(1_000_000_000)
+      .sub(vestingAccount.acceleratedAmount)
+      .divn(5);
+    assert.isTrue(passedPeriods > 0 && passedPeriods < 5);
+    assert.equal(
+      vestingAfter.claimedAmount.toString(),
+      vestingAccount.acceleratedAmount
+        .add(restPerPeriod.muln(passedPeriods))
+        .toString()
+    );
+  });
+
+  it("Splits part of a grant off to a new beneficiary", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+    } = await setupVesting(18, 1_000_000_000);
+    const newBeneficiary = anchor.web3.Keypair.generate();
+    const newIndex = new anchor.BN(0);
+    const [newVestingPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        newBeneficiary.publicKey.toBuffer(),
+        newIndex.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [newVaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), newVestingPda.toBuffer()],
+      program.programId
+    );
+    const startTime = await currentTimestamp();
+    const splitEvents = [];
+    const listener = program.addEventListener("grantSplit", (event) =>
+      splitEvents.push(event)
+    );
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(startTime),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 3000));
+
+    await program.methods
+      .splitVesting(index, newBeneficiary.publicKey, newIndex, 5000)
+      .accounts({
+        beneficiary: beneficiary.publicKey,
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        newVestingAccount: newVestingPda,
+        newVaultAta,
+        grantorAta: null,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([beneficiary, admin])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const newVestingAccount = await program.account.tokenVesting.fetch(
+      newVestingPda
+    );
+    const vault = await getAccount(connection, vaultAta);
+    const newVault = await getAccount(connection, newVaultAta);
+
+    // Each vault holds exactly what its grant still owes
+    assert.equal(
+      vault.amount.toString(),
+      vestingAccount.totalAmount.sub(vestingAccount.claimedAmount).toString()
+    );
+    assert.equal(
+      newVault.amount.toString(),
+      newVestingAccount.totalAmount
+        .sub(newVestingAccount.claimedAmount)
+        .toString()
+    );
+    assert.equal((vault.amount + newVault.amount).toString(), "1000000000");
+    // The new grant starts out as claimed up to the periods vested at the split
+    assert.ok(
+      newVestingAccount.passedPeriods.gte(vestingAccount.passedPeriods)
+    );
+    // The vested amount kept by the current grant is carried up front, it is no acceleration
+    assert.ok(vestingAccount.carriedAmount.gtn(0));
+    assert.equal(vestingAccount.acceleratedAmount.toNumber(), 0);
+
+    // The new grant's share of the tokens vested before the split is reported on the event
+    await new Promise((res) => setTimeout(res, 1000));
+    await program.removeEventListener(listener);
+    const preClaimedAmount = newVestingAccount.claimedAmount;
+    assert.ok(preClaimedAmount.gtn(0));
+    assert.equal(splitEvents.length, 1);
+    assert.equal(
+      splitEvents[0].preClaimedAmount.toString(),
+      preClaimedAmount.toString()
+    );
+    assert.equal(splitEvents[0].amount.toString(), newVault.amount.toString());
+
+    // Later on the two grants together vest like the unsplit grant, 200000000 every 2 seconds
+    await new Promise((res) => setTimeout(res, 2000));
+    const unsplitVestedAt = (claimable) => {
+      const periods =
+        claimable.nextUnlockTime === null
+          ? 5
+          : (claimable.nextUnlockTime.toNumber() - startTime) / 2 - 1;
+      return new anchor.BN(200_000_000).muln(periods);
+    };
+    const getClaimable = (vestingPda: anchor.web3.PublicKey, i: anchor.BN) =>
+      program.methods
+        .getClaimable(i)
+        .accounts({
+          vestingAccount: vestingPda,
+        })
+        .view();
+    const claimable = await getClaimable(vestingAccountPda, index);
+    const newClaimable = await getClaimable(newVestingPda, newIndex);
+
+    // The new grant vests its share of the original schedule, the current grant the rest
+    const unsplit = unsplitVestedAt(claimable);
+    const newUnsplit = unsplitVestedAt(newClaimable);
+    assert.ok(newUnsplit.gt(preClaimedAmount.muln(2)));
+    assert.equal(
+      newClaimable.vestedAmount.toString(),
+      newUnsplit.muln(5000).divn(10000).toString()
+    );
+    assert.equal(
+      claimable.vestedAmount.toString(),
+      unsplit
+        .sub(unsplit.muln(5000).divn(10000))
+        .add(preClaimedAmount)
+        .toString()
+    );
+  });
+
+  it("Claims a tokenized grant as the holder of the position NFT", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      await setupVesting(19, 1_000_000_000);
+    const holder = anchor.web3.Keypair.generate();
+    const [positionMint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
+    const positionAta = await getAssociatedTokenAddress(
+      positionMint,
+      beneficiary.publicKey
+    );
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(await currentTimestamp()),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        positionMint,
+        positionOwner: beneficiary.publicKey,
+        positionAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const position = await getMint(connection, positionMint);
+    assert.equal(position.supply.toString(), "1");
+    assert.isNull(position.mintAuthority);
+
+    // Hand the position over, the claim right moves with it
+    const holderPositionAta = await createAssociatedTokenAccount(
+      connection,
+      provider.wallet.payer,
+      positionMint,
+      holder.publicKey
+    );
+    await transfer(
+      connection,
+      provider.wallet.payer,
+      positionAta,
+      holderPositionAta,
+      beneficiary,
+      1
+    );
+    const holderAta = await createAssociatedTokenAccount(
+      connection,
+      provider.wallet.payer,
+      mint,
+      holder.publicKey
+    );
+
+    await new Promise((res) => setTimeout(res, 5000));
+
+    await program.methods
+      .claimVestedToken(index)
+      .accounts({
+        beneficiary: holder.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        beneficiaryAta: holderAta,
+        positionAta: holderPositionAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([holder])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const holderAccount = await getAccount(connection, holderAta);
+
+    assert.equal(
+      vestingAccount.positionMint.toBase58(),
+      positionMint.toBase58()
+    );
+    assert.equal(
+      holderAccount.amount.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+  });
+
+  it("Keeps vested tokens locked until the unlock time", async () => {
+    const {
+      mint,
+      userAta,
+      vaultAta,
+      vestingAccountPda,
+      index,
+      beneficiaryAta,
+    } = await setupVesting(20, 1_000_000_000);
+    const now = await currentTimestamp();
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(now),
+        false,
+        { periodic: {} },
+        new anchor.BN(now + 1000), // locked well past the end of vesting
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 5000));
+
+    try {
+      await program.methods
+        .claimVestedToken(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          beneficiaryAta: beneficiaryAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+      assert.fail("locked tokens were claimed");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "TokensLocked");
+    }
+
+    const claimable = await program.methods
+      .getClaimable(index)
+      .accounts({
+        vestingAccount: vestingAccountPda,
+      })
+      .view();
+
+    assert.ok(claimable.lockedAmount.gtn(0));
+    assert.equal(claimable.claimableAmount.toString(), "0");
+    assert.equal(
+      claimable.lockedAmount.toString(),
+      claimable.vestedAmount.toString()
+    );
+    assert.equal(claimable.nextUnlockTime.toNumber(), now + 1000);
+  });
+
+  it("Vests a monthly schedule on calendar month boundaries", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      await setupVesting(21, 1_200_000_000);
+    const now = await currentTimestamp();
+    const events = [];
+    const listener = program.addEventListener("grantCreated", (event) =>
+      events.push(event)
+    );
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(1), // 1 month
+        new anchor.BN(12), // 12 months
+        new anchor.BN(1_200_000_000),
+        index,
+        new anchor.BN(1), // 1 month
+        new anchor.BN(now),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { months: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    assert.deepEqual(vestingAccount.periodUnit, { months: {} });
+    assert.equal(vestingAccount.vestingPeriod.toNumber(), 1);
+
+    // The event reports the cliff in seconds like the account, not in months
+    await new Promise((res) => setTimeout(res, 1000));
+    await program.removeEventListener(listener);
+    assert.equal(events.length, 1);
+    assert.ok(vestingAccount.cliffDuration.gtn(27 * 24 * 60 * 60));
+    assert.equal(
+      events[0].cliffDuration.toString(),
+      vestingAccount.cliffDuration.toString()
+    );
+
+    // The duration is stored in seconds, it ends on the same date a year later
+    const start = new Date(now * 1000);
+    const end = new Date((now + vestingAccount.duration.toNumber()) * 1000);
+    assert.equal(end.getUTCFullYear(), start.getUTCFullYear() + 1);
+    assert.equal(end.getUTCMonth(), start.getUTCMonth());
+
+    const claimable = await program.methods
+      .getClaimable(index)
+      .accounts({
+        vestingAccount: vestingAccountPda,
+      })
+      .view();
+
+    // The first period ends a calendar month after the start
+    const nextUnlock = new Date(claimable.nextUnlockTime.toNumber() * 1000);
+    assert.equal(claimable.vestedAmount.toString(), "0");
+    assert.equal(nextUnlock.getUTCMonth(), (start.getUTCMonth() + 1) % 12);
+    assert.equal(nextUnlock.getUTCHours(), start.getUTCHours());
+    assert.equal(nextUnlock.getUTCMinutes(), start.getUTCMinutes());
+  });
+
+  it("Creates grants from a vesting template", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      await setupVesting(22, 1_000_000_000);
+    const templateId = new anchor.BN(0);
+    const [templatePda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("template"),
+        admin.publicKey.toBuffer(),
+        templateId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .createVestingTemplate(
+          templateId,
+          new anchor.BN(0),
+          new anchor.BN(0),
+          new anchor.BN(0),
+          {
+            tranched: {
+              tranches: [
+                { unlockTimestamp: new anchor.BN(1), amount: new anchor.BN(1) },
+              ],
+            },
+          },
+          false,
+          { seconds: {} }
+        )
+        .accounts({
+          authority: admin.publicKey,
+          template: templatePda,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("a tranche template was created");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "UnsupportedSchedule");
+    }
+
+    // 4 years vesting every quarter after a 1 year cliff
+    await program.methods
+      .createVestingTemplate(
+        templateId,
+        new anchor.BN(1),
+        new anchor.BN(16),
+        new anchor.BN(4),
+        { periodic: {} },
+        true,
+        { quarters: {} }
+      )
+      .accounts({
+        authority: admin.publicKey,
+        template: templatePda,
+      })
+      .signers([admin])
+      .rpc();
+
+    const now = await currentTimestamp();
+    await program.methods
+      .initializeVestingFromTemplate(
+        beneficiary.publicKey,
+        index,
+        new anchor.BN(1_000_000_000),
+        new anchor.BN(now)
+      )
+      .accounts({
+        authority: admin.publicKey,
+        template: templatePda,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        mint,
+        authorityAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const template = await program.account.vestingTemplate.fetch(templatePda);
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    assert.equal(template.authority.toBase58(), admin.publicKey.toBase58());
+    assert.equal(vestingAccount.grantor.toBase58(), admin.publicKey.toBase58());
+    assert.equal(vestingAccount.template.toBase58(), templatePda.toBase58());
+    assert.equal(vestingAccount.revocable, true);
+    assert.deepEqual(vestingAccount.periodUnit, { quarters: {} });
+    assert.equal(vestingAccount.vestingPeriod.toNumber(), 1);
+    assert.equal(vestingAccount.totalAmount.toString(), "1000000000");
+    // The cliff and duration are stored in seconds from the start
+    const start = new Date(now * 1000);
+    const cliff = new Date(
+      (now + vestingAccount.cliffDuration.toNumber()) * 1000
+    );
+    const end = new Date((now + vestingAccount.duration.toNumber()) * 1000);
+    assert.equal(cliff.getUTCFullYear(), start.getUTCFullYear() + 1);
+    assert.equal(end.getUTCFullYear(), start.getUTCFullYear() + 4);
+
+    const vault = await getAccount(connection, vaultAta);
+    assert.equal(vault.amount.toString(), "1000000000");
+
+    // The template sits at a fixed offset, so the grants made from it can be listed
+    const templateOffset = 8 + 114 + 1 + 32 * 4;
+    const grants = await program.account.tokenVesting.all([
+      { memcmp: { offset: templateOffset, bytes: templatePda.toBase58() } },
+    ]);
+    assert.equal(grants.length, 1);
+    assert.ok(grants[0].publicKey.equals(vestingAccountPda));
+  });
+
+  it("Only lets the beneficiary claim into a staking position", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      await setupVesting(23, 1_000_000_000);
+    const now = await currentTimestamp();
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(now),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await new Promise((res) => setTimeout(res, 3000));
+
+    const attacker = anchor.web3.Keypair.generate();
+    const stakePool = anchor.web3.Keypair.generate().publicKey;
+    try {
+      await program.methods
+        .claimAndStake(index)
+        .accounts({
+          beneficiary: attacker.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          stakingProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          stakePool,
+          stakePosition: attacker.publicKey,
+          stakePoolVault: stakePool,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([attacker])
+        .rpc();
+      assert.fail("a stranger staked the claim");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "UnauthorizedBeneficiary");
+    }
+
+    const vault = await getAccount(connection, vaultAta);
+    assert.equal(vault.amount.toString(), "1000000000");
+  });
+
+  it("Claims into a staking position and rejects incomplete deposits", async () => {
+    const { mint, vaultAta, vestingAccountPda, index } = vestingsParams[23];
+    const [stakeEscrow] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("stake_escrow"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
+
+    async function createStakePool(shortfall: number) {
+      const pool = anchor.web3.Keypair.generate();
+      const [poolVault] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("pool_vault"), pool.publicKey.toBuffer()],
+        stakingProgram.programId
+      );
+      const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("position"),
+          pool.publicKey.toBuffer(),
+          beneficiary.publicKey.toBuffer(),
+        ],
+        stakingProgram.programId
+      );
+
+      await stakingProgram.methods
+        .initializePool(new anchor.BN(shortfall))
+        .accounts({
+          payer: admin.publicKey,
+          pool: pool.publicKey,
+          poolVault,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin, pool])
+        .rpc();
+      await stakingProgram.methods
+        .openPosition()
+        .accounts({
+          owner: beneficiary.publicKey,
+          pool: pool.publicKey,
+          position,
+        })
+        .signers([beneficiary])
+        .rpc();
+
+      return { pool: pool.publicKey, poolVault, position };
+    }
+
+    const claimAndStake = (stake: {
+      pool: anchor.web3.PublicKey;
+      poolVault: anchor.web3.PublicKey;
+      position: anchor.web3.PublicKey;
+    }) =>
+      program.methods
+        .claimAndStake(index)
+        .accounts({
+          beneficiary: beneficiary.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          stakeEscrow,
+          stakingProgram: stakingProgram.programId,
+          stakePool: stake.pool,
+          stakePosition: stake.position,
+          stakePoolVault: stake.poolVault,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([beneficiary])
+        .rpc();
+
+    const stake = await createStakePool(0);
+    await claimAndStake(stake);
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    const position = await stakingProgram.account.position.fetch(
+      stake.position
+    );
+    const poolVault = await getAccount(connection, stake.poolVault);
+    const escrow = await getAccount(connection, stakeEscrow);
+
+    assert.ok(vestingAccount.claimedAmount.gtn(0));
+    assert.equal(
+      position.staked.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+    assert.equal(
+      poolVault.amount.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+    assert.equal(escrow.amount.toString(), "0");
+
+    // A pool that pulls in less than the claim would leave tokens behind in the escrow
+    const shortStake = await createStakePool(1);
+    await new Promise((res) => setTimeout(res, 2000));
+    try {
+      await claimAndStake(shortStake);
+      assert.fail("expected IncompleteStakeDeposit");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "IncompleteStakeDeposit");
+    }
+
+    const vestingAfter = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    assert.equal(
+      vestingAfter.claimedAmount.toString(),
+      vestingAccount.claimedAmount.toString()
+    );
+  });
+
+  it("Withdraws tokens sent to the vault on top of the grant", async () => {
+    const { mint, userAta, vaultAta, vestingAccountPda, index } =
+      await setupVesting(24, 1_000_000_000);
+    const now = await currentTimestamp();
+
+    await program.methods
+      .initializeVesting(
+        mint,
+        beneficiary.publicKey,
+        new anchor.BN(2),
+        new anchor.BN(10),
+        new anchor.BN(1_000_000_000),
+        index,
+        new anchor.BN(0),
+        new anchor.BN(now),
+        false,
+        { periodic: {} },
+        new anchor.BN(0),
+        { seconds: {} }
+      )
+      .accounts({
+        user: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta: vaultAta,
+        mint,
+        adminAta: userAta,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    // Tokens sent straight to the vault are not part of the grant
+    await transfer(
+      connection,
+      provider.wallet.payer,
+      userAta,
+      vaultAta,
+      admin,
+      250_000_000
+    );
+    const grantorBefore = await getAccount(connection, userAta);
+
+    await program.methods
+      .withdrawSurplus(index)
+      .accounts({
+        grantor: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        vaultAta,
+        grantorAta: userAta,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vault = await getAccount(connection, vaultAta);
+    const grantorAfter = await getAccount(connection, userAta);
+    assert.equal(vault.amount.toString(), "1000000000");
+    assert.equal(
+      (grantorAfter.amount - grantorBefore.amount).toString(),
+      "250000000"
+    );
+
+    try {
+      await program.methods
+        .withdrawSurplus(index)
+        .accounts({
+          grantor: admin.publicKey,
+          vestingAccount: vestingAccountPda,
+          vaultAta,
+          grantorAta: userAta,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("the grant was withdrawn as surplus");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "NoSurplus");
+    }
+  });
+
+  it("Migrates a version 1 grant to the current layout", async () => {
+    // Loaded from tests/fixtures, laid out as the accounts of version 1 of the program
+    const mint = new anchor.web3.PublicKey(
+      "JDkLGERpCAG47WifhZzreEb8KMMTrXmrTjXe78tSDfCX"
+    );
+    const legacyBeneficiary = new anchor.web3.PublicKey(
+      "Bq59F5wSuH1M2ybpQoeZsejEffZkY6WPLEJ3PEhHt1p9"
+    );
+    const index = new anchor.BN(100);
+    const [vestingAccountPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vesting"),
+        legacyBeneficiary.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [legacyVault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vault"),
+        mint.toBuffer(),
+        index.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [vaultAta] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vestingAccountPda.toBuffer()],
+      program.programId
+    );
+
+    const legacyAccount = await connection.getAccountInfo(vestingAccountPda);
+    assert.equal(legacyAccount.data.length, 122);
+
+    await program.methods
+      .migrateVesting(index)
+      .accounts({
+        payer: admin.publicKey,
+        vestingAccount: vestingAccountPda,
+        legacyVault,
+        vaultAta,
+        payerAta: null,
+        mint,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    const vestingAccount = await program.account.tokenVesting.fetch(
+      vestingAccountPda
+    );
+    assert.equal(vestingAccount.version, 2);
+    assert.ok(vestingAccount.beneficiary.equals(legacyBeneficiary));
+    assert.ok(vestingAccount.originalBeneficiary.equals(legacyBeneficiary));
+    assert.ok(vestingAccount.mint.equals(mint));
+    assert.equal(vestingAccount.vestingPeriod.toNumber(), 2);
+    assert.equal(vestingAccount.duration.toNumber(), 10);
+    assert.equal(vestingAccount.totalAmount.toString(), "1000000000");
+    assert.equal(vestingAccount.startTime.toNumber(), 1_700_000_000);
+    assert.equal(vestingAccount.claimedAmount.toString(), "400000000");
+    assert.equal(vestingAccount.passedPeriods.toNumber(), 2);
+    assert.equal(vestingAccount.acceleratedAmount.toNumber(), 0);
+    assert.isFalse(vestingAccount.revocable);
+    assert.deepEqual(vestingAccount.curve, { periodic: {} });
+
+    const vault = await getAccount(connection, vaultAta);
+    assert.equal(vault.amount.toString(), "600000000");
+    assert.isNull(await connection.getAccountInfo(legacyVault));
+
+    const claimable = await program.methods
+      .getClaimable(index)
+      .accounts({
+        vestingAccount: vestingAccountPda,
+      })
+      .view();
+    assert.equal(claimable.claimableAmount.toString(), "600000000");
+
+    try {
+      await program.methods
+        .migrateVesting(index)
+        .accounts({
+          payer: admin.publicKey,
+          vestingAccount: vestingAccountPda,
+          legacyVault: null,
+          vaultAta: null,
+          payerAta: null,
+          mint,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("the grant was migrated twice");
+    } catch (err) {
+      const anchorError = err as anchor.AnchorError;
+
+      assert.equal(anchorError.error.errorCode.code, "AlreadyMigrated");
+    }
+  });
 });